@@ -0,0 +1,93 @@
+//! 固定長のビット集合
+
+/// 固定長のビット集合
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// 要素数 `len` の空集合
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// `0..len` をすべて含む集合
+    pub(crate) fn full(len: usize) -> Self {
+        let mut set = Self::new(len);
+        for i in 0..len {
+            set.insert(i);
+        }
+        set
+    }
+
+    pub(crate) fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub(crate) fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    /// `self |= other`
+    pub(crate) fn union_with(&mut self, other: &BitSet) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    /// `self \ other` が空か
+    pub(crate) fn is_subset(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .all(|(a, b)| a & !b == 0)
+    }
+
+    pub(crate) fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub(crate) fn words_mut(&mut self) -> &mut [u64] {
+        &mut self.words
+    }
+
+    /// 含まれる要素を昇順に返す
+    pub(crate) fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_bitset {
+    use super::BitSet;
+
+    #[test]
+    fn set_operations() {
+        let mut a = BitSet::new(130);
+        for i in [0, 64, 129] {
+            a.insert(i);
+        }
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![0, 64, 129]);
+
+        let mut b = BitSet::new(130);
+        b.insert(64);
+        assert!(b.is_subset(&a));
+        assert!(!a.is_subset(&b));
+
+        b.union_with(&a);
+        assert_eq!(b, a);
+        assert_eq!(BitSet::full(130).iter().count(), 130);
+    }
+}
@@ -0,0 +1,177 @@
+//! CNF（DIMACS 形式）への変換
+
+use itertools::Itertools;
+
+use crate::{Field, Solution, solver::fast::Core};
+
+/// 盤面を表す CNF 論理式
+///
+/// 変数 `v`（1 始まり）は `v - 1` 番目の空白セルにあかりを置くことを表す．
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cnf {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+    h: usize,
+    w: usize,
+    /// 変数 `v` に対応するセルは `vars[v - 1]`
+    vars: Vec<(usize, usize)>,
+}
+
+impl Cnf {
+    /// 盤面を CNF に変換する
+    ///
+    /// - 各セグメントにあかりは高々 1 つ（ペアごとの節）
+    /// - 数字セルの周りのあかりはちょうど数字の個数（部分集合ごとの節）
+    /// - 各空白セルは同じ行または列のセグメントのどこかにあかりがある
+    pub fn encode(field: &Field) -> Self {
+        let core = Core::new(field);
+        let var = |cell: usize| cell as i32 + 1;
+        let mut clauses = Vec::new();
+
+        for cells in core.segments() {
+            for (&a, &b) in cells.iter().tuple_combinations() {
+                clauses.push(vec![-var(a), -var(b)]);
+            }
+        }
+
+        for (value, adj) in core.numbers() {
+            if value > adj.len() {
+                // 充足不能
+                clauses.push(vec![]);
+                continue;
+            }
+            // value + 1 個のうち少なくとも 1 つは置かない
+            for subset in adj.iter().combinations(value + 1) {
+                clauses.push(subset.into_iter().map(|&x| -var(x)).collect());
+            }
+            // adj.len() - value + 1 個のうち少なくとも 1 つは置く
+            for subset in adj.iter().combinations(adj.len() - value + 1) {
+                clauses.push(subset.into_iter().map(|&x| var(x)).collect());
+            }
+        }
+
+        for cell in 0..core.empty_pos().len() {
+            clauses.push(core.lit_cells(cell).map(var).collect());
+        }
+
+        Cnf {
+            num_vars: core.empty_pos().len(),
+            clauses,
+            h: field.h,
+            w: field.w,
+            vars: core.empty_pos().to_vec(),
+        }
+    }
+
+    /// セル `(r, c)` に対応する変数
+    pub fn var(&self, r: usize, c: usize) -> Option<i32> {
+        self.vars
+            .iter()
+            .position(|&pos| pos == (r, c))
+            .map(|i| i as i32 + 1)
+    }
+
+    /// DIMACS 形式の文字列
+    pub fn to_dimacs(&self) -> String {
+        let mut s = format!("c akari {}x{}\n", self.h, self.w);
+        s += &format!("p cnf {} {}\n", self.num_vars, self.clauses.len());
+        for clause in &self.clauses {
+            for lit in clause {
+                s += &format!("{} ", lit);
+            }
+            s += "0\n";
+        }
+        s
+    }
+
+    /// 充足割り当て（正のリテラルが真）を解に戻す
+    pub fn decode(&self, model: &[i32]) -> Solution {
+        let mut field = vec![vec![false; self.w]; self.h];
+        for &lit in model {
+            if lit > 0 && lit as usize <= self.num_vars {
+                let (r, c) = self.vars[lit as usize - 1];
+                field[r][c] = true;
+            }
+        }
+        Solution { field }
+    }
+}
+
+/// SAT ソルバの出力から `v` 行のリテラルを読み取る
+///
+/// `s UNSATISFIABLE` の場合や数値として読めない場合は `None` を返す．
+pub fn parse_model(output: &str) -> Option<Vec<i32>> {
+    let mut model = Vec::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("s UNSAT") {
+            return None;
+        }
+        let Some(rest) = line.strip_prefix('v') else {
+            continue;
+        };
+        for token in rest.split_whitespace() {
+            match token.parse::<i32>().ok()? {
+                0 => {}
+                lit => model.push(lit),
+            }
+        }
+    }
+    Some(model)
+}
+
+#[cfg(test)]
+mod test_cnf {
+    use crate::{
+        Field, Solution,
+        cnf::{Cnf, parse_model},
+        solver::Fast,
+    };
+
+    /// 全割り当てを試して充足するものを列挙する
+    fn brute_force(cnf: &Cnf) -> Vec<Solution> {
+        (0..1u32 << cnf.num_vars)
+            .map(|bits| {
+                (1..=cnf.num_vars as i32)
+                    .map(|v| if bits >> (v - 1) & 1 == 1 { v } else { -v })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|model| {
+                cnf.clauses
+                    .iter()
+                    .all(|clause| clause.iter().any(|lit| model.contains(lit)))
+            })
+            .map(|model| cnf.decode(&model))
+            .collect()
+    }
+
+    #[test]
+    fn models_match_solutions() {
+        for (h, w, s) in [
+            (3, 3, "2.1 ... ..0"),
+            (3, 3, "... .#. ..."),
+            (2, 3, ".4. ..."),
+            (3, 3, "0.0 ### 1.."),
+        ] {
+            let field = Field::from_str(h, w, s).unwrap();
+            let cnf = Cnf::encode(&field);
+            let mut models = brute_force(&cnf);
+            let mut sols: Vec<_> = Fast::new().solutions(&field).collect();
+            models.sort_by(|a, b| a.field.cmp(&b.field));
+            sols.sort_by(|a, b| a.field.cmp(&b.field));
+            assert_eq!(models, sols, "{}", s);
+        }
+    }
+
+    #[test]
+    fn dimacs_round_trip() {
+        let field = Field::from_str(1, 3, ".1#").unwrap();
+        let cnf = Cnf::encode(&field);
+        assert_eq!(cnf.to_dimacs(), "c akari 1x3\np cnf 1 2\n1 0\n1 0\n");
+
+        let output = "c comment\ns SATISFIABLE\nv 1 0\n";
+        let model = parse_model(output).unwrap();
+        assert_eq!(cnf.decode(&model).field, vec![vec![true, false, false]]);
+        assert_eq!(parse_model("s UNSATISFIABLE\n"), None);
+    }
+}
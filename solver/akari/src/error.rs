@@ -0,0 +1,64 @@
+//! エラー
+
+use std::fmt;
+
+/// 盤面の解析エラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// セルとして解釈できない文字
+    InvalidCell(char),
+    /// `row` 行 `col` 列の文字がセルとして解釈できない
+    InvalidChar { row: usize, col: usize, ch: char },
+    /// `row` 行 `col` 列のトークンが 1 文字でない
+    InvalidToken {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+    /// `row` 行の幅が一致しない
+    RowWidth {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// 行数が一致しない
+    RowCount { expected: usize, actual: usize },
+    /// 盤面が空
+    Empty,
+    /// puzz.link の URL として解釈できない
+    InvalidUrl(String),
+    /// ファイルの `line` 行目（0 始まり）のヘッダが不正
+    InvalidHeader { line: usize },
+    /// 指定された `h` 行 `w` 列の盤面が大きすぎる（縦横それぞれ `max` まで）
+    TooLarge { h: usize, w: usize, max: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCell(ch) => write!(f, "invalid cell character {ch:?}"),
+            Self::InvalidChar { row, col, ch } => {
+                write!(f, "invalid character {ch:?} at row {row}, column {col}")
+            }
+            Self::InvalidToken { row, col, token } => {
+                write!(f, "invalid token {token:?} at row {row}, column {col}")
+            }
+            Self::RowWidth {
+                row,
+                expected,
+                actual,
+            } => write!(f, "row {row} has width {actual}, expected {expected}"),
+            Self::RowCount { expected, actual } => {
+                write!(f, "board has {actual} rows, expected {expected}")
+            }
+            Self::Empty => write!(f, "board is empty"),
+            Self::InvalidUrl(url) => write!(f, "invalid puzz.link URL {url:?}"),
+            Self::InvalidHeader { line } => write!(f, "invalid header at line {line}"),
+            Self::TooLarge { h, w, max } => {
+                write!(f, "board of {h}x{w} is too large (at most {max}x{max})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
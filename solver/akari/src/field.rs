@@ -1,5 +1,7 @@
 //! フィールド
 
+use crate::error::ParseError;
+
 /// フィールドの状態
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum State {
@@ -25,7 +27,7 @@ impl State {
     /// - `#`: セルなし
     /// - `.`: 空白セル
     /// - `0` - `4`: あかりが隣接するセル
-    pub fn from_char(c: char) -> Result<Self, &'static str> {
+    pub fn from_char(c: char) -> Result<Self, ParseError> {
         match c {
             '#' => Ok(Self::Nil),
             '.' => Ok(Self::Empty),
@@ -34,7 +36,32 @@ impl State {
             '2' => Ok(Self::Adj2),
             '3' => Ok(Self::Adj3),
             '4' => Ok(Self::Adj4),
-            _ => Err("cell parse error"),
+            _ => Err(ParseError::InvalidCell(c)),
+        }
+    }
+
+    /// 1 文字で表す（[`State::from_char`] の逆）
+    pub fn to_char(&self) -> char {
+        match self {
+            Self::Nil => '#',
+            Self::Empty => '.',
+            Self::Adj0 => '0',
+            Self::Adj1 => '1',
+            Self::Adj2 => '2',
+            Self::Adj3 => '3',
+            Self::Adj4 => '4',
+        }
+    }
+
+    /// 隣接するあかりの数から数字セルを作る
+    pub fn from_adj(n: usize) -> Option<Self> {
+        match n {
+            0 => Some(Self::Adj0),
+            1 => Some(Self::Adj1),
+            2 => Some(Self::Adj2),
+            3 => Some(Self::Adj3),
+            4 => Some(Self::Adj4),
+            _ => None,
         }
     }
 
@@ -55,7 +82,7 @@ impl State {
 }
 
 /// フィールド
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Field {
     pub h: usize,
     pub w: usize,
@@ -68,27 +95,38 @@ impl Field {
     /// - `#`: セルなし
     /// - `.`: 空白セル
     /// - `0` - `4`: あかりが隣接するセル
-    pub fn from_str(h: usize, w: usize, s: &str) -> Result<Field, &'static str> {
+    pub fn from_str(h: usize, w: usize, s: &str) -> Result<Field, ParseError> {
         let field = s
             .split_ascii_whitespace()
-            .map(|row| {
-                row.chars()
-                    .map(State::from_char)
-                    .collect::<Result<Vec<State>, &'static str>>()
-            })
-            .map(|row| {
-                row.and_then(|r| {
-                    if r.len() == w {
-                        Ok(r)
-                    } else {
-                        Err("failed to parse")
-                    }
-                })
+            .enumerate()
+            .map(|(row, line)| {
+                let cells = line
+                    .chars()
+                    .enumerate()
+                    .map(|(col, ch)| {
+                        State::from_char(ch).map_err(|_| ParseError::InvalidChar { row, col, ch })
+                    })
+                    .collect::<Result<Vec<State>, ParseError>>()?;
+                if cells.len() == w {
+                    Ok(cells)
+                } else {
+                    Err(ParseError::RowWidth {
+                        row,
+                        expected: w,
+                        actual: cells.len(),
+                    })
+                }
             })
-            .collect::<Result<Vec<Vec<State>>, &'static str>>()?;
+            .collect::<Result<Vec<Vec<State>>, ParseError>>()?;
 
+        if field.is_empty() {
+            return Err(ParseError::Empty);
+        }
         if field.len() != h {
-            return Err("failed to parse");
+            return Err(ParseError::RowCount {
+                expected: h,
+                actual: field.len(),
+            });
         }
 
         Ok(Field { h, w, field })
@@ -142,12 +180,58 @@ impl Solution {
             })
             .collect()
     }
+
+    /// 他の解とあかりの有無が異なるセルのリストを返す
+    pub fn diff(&self, other: &Solution) -> Vec<(usize, usize)> {
+        self.field
+            .iter()
+            .zip(&other.field)
+            .enumerate()
+            .flat_map(|(r, (row, other_row))| {
+                row.iter()
+                    .zip(other_row)
+                    .enumerate()
+                    .filter_map(move |(c, (a, b))| (a != b).then_some((r, c)))
+            })
+            .collect()
+    }
+}
+
+/// 途中盤面のセルの印
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// 未確定
+    Unknown,
+    /// あかりを置いた
+    Light,
+    /// あかりを置かないと印をつけた
+    Dot,
+}
+
+/// 途中盤面
+///
+/// 空白セル以外の印は無視される．
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialSolution {
+    pub field: Vec<Vec<Mark>>,
+}
+
+impl PartialSolution {
+    /// すべてのセルが未確定の途中盤面を作る
+    pub fn new(h: usize, w: usize) -> Self {
+        Self {
+            field: vec![vec![Mark::Unknown; w]; h],
+        }
+    }
 }
 
 // ========== テスト ==========
 #[cfg(test)]
 mod test_field {
-    use crate::field::{Field, State};
+    use crate::{
+        error::ParseError,
+        field::{Field, State},
+    };
 
     #[test]
     fn test_parse_field_success() {
@@ -207,6 +291,30 @@ mod test_field {
 ";
         let field_actual = Field::from_str(4, 5, field_str);
         eprintln!("{field_actual:?}");
-        assert!(field_actual.is_err());
+        assert_eq!(
+            field_actual,
+            Err(ParseError::RowWidth {
+                row: 2,
+                expected: 5,
+                actual: 4
+            })
+        );
+
+        assert_eq!(
+            Field::from_str(1, 3, ".x."),
+            Err(ParseError::InvalidChar {
+                row: 0,
+                col: 1,
+                ch: 'x'
+            })
+        );
+        assert_eq!(
+            Field::from_str(3, 3, "... ..."),
+            Err(ParseError::RowCount {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(Field::from_str(0, 0, "\n"), Err(ParseError::Empty));
     }
 }
@@ -0,0 +1,353 @@
+//! 一意解をもつ盤面の生成
+
+use crate::{
+    field::{Field, Solution, State},
+    solver::{Cancel, Fast, Uniqueness},
+    utility::GridUtility,
+    validate::{illuminated, lit_cells},
+};
+
+/// 壁の配置の対称性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// 対称性なし
+    None,
+    /// 180° 回転対称
+    Rotational,
+    /// 左右対称
+    Mirror,
+}
+
+/// 盤面の生成器
+///
+/// 同じ設定とシードからは常に同じ盤面が生成される．
+#[derive(Debug, Clone, Copy)]
+pub struct Generator {
+    pub h: usize,
+    pub w: usize,
+    /// 壁にするセルの割合（0.0 - 1.0）
+    pub wall_density: f64,
+    pub symmetry: Symmetry,
+    /// 一意性の判定 1 回あたりの作業量の上限（[`Cancel::with_budget`]）
+    ///
+    /// 上限までに判定できなければ，盤面を直す段階では壁を足し，数字を取り除く段階ではその数字を残す．
+    pub check_budget: usize,
+}
+
+impl Generator {
+    /// 盤面の大きさを指定して生成器を構築する
+    pub fn new(h: usize, w: usize) -> Self {
+        Self {
+            h,
+            w,
+            wall_density: 0.2,
+            symmetry: Symmetry::Rotational,
+            check_budget: 20_000,
+        }
+    }
+
+    /// 一意解をもち，どの数字を取り除いても一意でなくなる盤面を生成する
+    ///
+    /// ただし取り除けるかを `check_budget` までに判定できなかった数字は残す．
+    pub fn generate(&self, seed: u64) -> Field {
+        self.generate_with(seed, &Cancel::new())
+            .expect("generation without a cancel always completes")
+    }
+
+    /// `cancel` で打ち切られるまでに [`Generator::generate`] と同じ盤面を生成する
+    ///
+    /// 一意性の判定が打ち切られれば `None` を返す．
+    ///
+    /// あかりの配置を 1 つ決めてから壁に数字を書くので，盤面は常にその配置を解にもつ．
+    /// 別の解が見つかれば，2 つの解で食い違うセル（と対になるセル）を壁にしてあかりを置き直す．
+    /// `check_budget` までに一意か判定できなければ，壁から離れたセルを壁にして手掛かりを増やす．
+    /// 壁は増える一方なので，盤面を作り直さなくても有限回で一意になる．
+    pub fn generate_with(&self, seed: u64, cancel: &Cancel) -> Option<Field> {
+        let mut rng = Rng::new(seed);
+        let solver = Fast::new();
+        let mut field = self.place_walls(&mut rng);
+        let mut light = Solution {
+            field: vec![vec![false; self.w]; self.h],
+        };
+
+        loop {
+            Self::place_lights(&field, &mut light, &mut rng);
+            Self::place_numbers(&mut field, &light);
+            let check = cancel.clone().with_budget(self.check_budget);
+            let (r, c) = {
+                let mut solutions = solver.solutions_with(&field, &check);
+                match (solutions.next(), solutions.next()) {
+                    (Some(a), Some(b)) => {
+                        Self::ambiguous_cell(&a.field, &b.field, &light.field, &mut rng)
+                    }
+                    _ if !solutions.is_interrupted() => break,
+                    _ if cancel.is_expired() => return None,
+                    _ => Self::open_cell(&field, &light.field, &mut rng),
+                }
+            };
+
+            for (r, c) in [(r, c), self.mirror(r, c)] {
+                field.field[r][c] = State::Nil;
+                light.field[r][c] = false;
+            }
+        }
+
+        self.minimize(&mut field, &mut rng, cancel).then_some(field)
+    }
+
+    /// 対称性を保って壁を配置する
+    fn place_walls(&self, rng: &mut Rng) -> Field {
+        let (h, w) = (self.h, self.w);
+        let mut field = vec![vec![State::Empty; w]; h];
+        for r in 0..h {
+            for c in 0..w {
+                let (mr, mc) = self.mirror(r, c);
+                // 対になるセルのうち先に現れる方で決める
+                if (mr, mc) < (r, c) {
+                    field[r][c] = field[mr][mc];
+                } else if rng.chance(self.wall_density) {
+                    field[r][c] = State::Nil;
+                }
+            }
+        }
+        Field { h, w, field }
+    }
+
+    /// (r, c) と対になるセル
+    fn mirror(&self, r: usize, c: usize) -> (usize, usize) {
+        match self.symmetry {
+            Symmetry::None => (r, c),
+            Symmetry::Rotational => (self.h - 1 - r, self.w - 1 - c),
+            Symmetry::Mirror => (r, self.w - 1 - c),
+        }
+    }
+
+    /// ランダムな順に照らされていないセルへあかりを置き，すべてのセルを照らす
+    ///
+    /// `light` にすでに置かれているあかりはそのまま残す．
+    fn place_lights(field: &Field, light: &mut Solution, rng: &mut Rng) {
+        let (h, w) = (field.h, field.w);
+        let mut cells: Vec<_> = (0..h * w)
+            .map(|i| (i / w, i % w))
+            .filter(|&(r, c)| field.field[r][c].is_empty())
+            .collect();
+        rng.shuffle(&mut cells);
+
+        let mut lit = lit_cells(field, light);
+        for (r, c) in cells {
+            if lit[r][c] {
+                continue;
+            }
+            light.field[r][c] = true;
+            for (nr, nc) in illuminated(field, (r, c)) {
+                lit[nr][nc] = true;
+            }
+        }
+    }
+
+    /// すべての壁に隣接するあかりの数を書く
+    fn place_numbers(field: &mut Field, light: &Solution) {
+        let (h, w) = (field.h, field.w);
+        for r in 0..h {
+            for c in 0..w {
+                if !field.field[r][c].is_empty() {
+                    let n = (r, c).adj(h, w).filter(|&(r, c)| light.field[r][c]).count();
+                    field.field[r][c] = State::from_adj(n).unwrap();
+                }
+            }
+        }
+    }
+
+    /// 2 つの解 `a`，`b` で食い違い，`light` ではあかりのないセルを選ぶ
+    ///
+    /// このセルを壁にすると `light` を保ったまま `a` か `b` の少なくとも一方が解でなくなる．
+    /// 解は互いに包含しないので，`light` と異なる方の解にはそのようなセルが必ずある．
+    fn ambiguous_cell(
+        a: &[Vec<bool>],
+        b: &[Vec<bool>],
+        light: &[Vec<bool>],
+        rng: &mut Rng,
+    ) -> (usize, usize) {
+        let candidates = |strict: bool| -> Vec<(usize, usize)> {
+            (0..light.len())
+                .flat_map(|r| (0..light[r].len()).map(move |c| (r, c)))
+                .filter(|&(r, c)| (a[r][c] || b[r][c]) && !light[r][c])
+                .filter(|&(r, c)| !strict || a[r][c] != b[r][c])
+                .collect()
+        };
+        let mut cells = candidates(true);
+        if cells.is_empty() {
+            cells = candidates(false);
+        }
+        cells[(rng.next_u64() % cells.len() as u64) as usize]
+    }
+
+    /// 一意か判定できなかった盤面で，次に壁にするセルを選ぶ
+    ///
+    /// 判定が難しいのは手掛かりの少ない広い領域なので，壁に隣接せず `light` でもあかりのない
+    /// 空白セルから選ぶ．なければ空白セルから選ぶ．
+    fn open_cell(field: &Field, light: &[Vec<bool>], rng: &mut Rng) -> (usize, usize) {
+        let (h, w) = (field.h, field.w);
+        let empty: Vec<_> = (0..h * w)
+            .map(|i| (i / w, i % w))
+            .filter(|&(r, c)| field.field[r][c].is_empty())
+            .collect();
+        let open: Vec<_> = empty
+            .iter()
+            .copied()
+            .filter(|&(r, c)| {
+                !light[r][c] && (r, c).adj(h, w).all(|(r, c)| field.field[r][c].is_empty())
+            })
+            .collect();
+        let cells = if open.is_empty() { &empty } else { &open };
+        cells[(rng.next_u64() % cells.len() as u64) as usize]
+    }
+
+    /// 一意性を保ったまま取り除ける数字をランダムな順に取り除く
+    ///
+    /// 数字を取り除くと解は増える一方なので，1 度取り除けなかった数字は以降も取り除けない．
+    /// 判定が `cancel` で打ち切られれば `false` を返す．
+    fn minimize(&self, field: &mut Field, rng: &mut Rng, cancel: &Cancel) -> bool {
+        let solver = Fast::new();
+        let mut numbers: Vec<_> = (0..field.h * field.w)
+            .map(|i| (i / field.w, i % field.w))
+            .filter(|&(r, c)| field.field[r][c].is_adj().is_some())
+            .collect();
+        rng.shuffle(&mut numbers);
+
+        for (r, c) in numbers {
+            let prev = field.field[r][c];
+            field.field[r][c] = State::Nil;
+            let check = cancel.clone().with_budget(self.check_budget);
+            match solver.uniqueness_with(field, &check) {
+                Uniqueness::Unique(_) => {}
+                Uniqueness::Interrupted if cancel.is_expired() => return false,
+                _ => field.field[r][c] = prev,
+            }
+        }
+        true
+    }
+}
+
+/// シードから再現可能な疑似乱数生成器（xorshift64*）
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64 で撹拌し，状態が 0 にならないようにする
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// 確率 p で true を返す
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// Fisher–Yates シャッフル
+    fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            v.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_generator {
+    use instant::Duration;
+
+    use crate::{
+        field::State,
+        generator::{Generator, Symmetry},
+        solver::{Cancel, Fast, Uniqueness},
+    };
+
+    #[test]
+    fn test_generate_unique_and_minimal() {
+        let generator = Generator::new(7, 7);
+        let field = generator.generate(42);
+        let solver = Fast::new();
+        assert!(matches!(solver.uniqueness(&field), Uniqueness::Unique(_)));
+
+        // 再現性
+        assert_eq!(generator.generate(42), field);
+
+        // 対称性
+        for r in 0..7 {
+            for c in 0..7 {
+                assert_eq!(
+                    field.field[r][c].is_empty(),
+                    field.field[6 - r][6 - c].is_empty()
+                );
+            }
+        }
+
+        // どの数字も取り除けない
+        for r in 0..7 {
+            for c in 0..7 {
+                if field.field[r][c].is_adj().is_some() {
+                    let mut removed = field.clone();
+                    removed.field[r][c] = State::Nil;
+                    assert_eq!(solver.uniqueness(&removed), Uniqueness::Multiple);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirror_symmetry() {
+        let generator = Generator {
+            symmetry: Symmetry::Mirror,
+            wall_density: 0.3,
+            ..Generator::new(6, 8)
+        };
+        let field = generator.generate(7);
+        for row in &field.field {
+            for c in 0..8 {
+                assert_eq!(row[c].is_empty(), row[7 - c].is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_large_boards() {
+        let solver = Fast::new();
+        for seed in 0..4 {
+            let field = Generator::new(20, 20).generate(seed);
+            assert!(matches!(solver.uniqueness(&field), Uniqueness::Unique(_)));
+        }
+        let generator = Generator {
+            wall_density: 0.1,
+            symmetry: Symmetry::None,
+            ..Generator::new(20, 20)
+        };
+        let field = generator.generate(0);
+        assert!(matches!(solver.uniqueness(&field), Uniqueness::Unique(_)));
+
+        // 予算を使い切れば打ち切られる
+        let cancel = Cancel::new().with_budget(10);
+        assert_eq!(generator.generate_with(0, &cancel), None);
+    }
+
+    #[test]
+    fn test_generate_sparse_board_within_deadline() {
+        // 手掛かりの少ない広い盤面でも一意性の判定が長引かない
+        let generator = Generator {
+            wall_density: 0.08,
+            ..Generator::new(30, 30)
+        };
+        let cancel = Cancel::after(Duration::from_secs(120));
+        assert!(generator.generate_with(0, &cancel).is_some());
+    }
+}
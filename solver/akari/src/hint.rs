@@ -0,0 +1,194 @@
+//! 人間が使う推論規則による 1 手ずつのヒント
+
+use crate::{
+    field::{Field, Mark, PartialSolution, Solution},
+    utility::GridUtility,
+    validate::{illuminated, lit_cells},
+};
+
+/// ヒントの根拠となる推論規則
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// 数字の周りにすでに必要な数のあかりがあるので，残りには置けない
+    NumberSatisfied { number: (usize, usize) },
+    /// 数字の周りの置けるセルすべてにあかりが必要
+    NumberNeedsAll { number: (usize, usize) },
+    /// 照らされていないセル `target` を照らせるセルが 1 つしかない
+    SingleCandidate { target: (usize, usize) },
+    /// 同じ行または列のセグメントにすでにあかり `light` がある
+    SegmentHasLight { light: (usize, usize) },
+}
+
+/// 次に確定する 1 手
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hint {
+    /// 確定するセル
+    pub pos: (usize, usize),
+    /// 確定する印（`Mark::Light` か `Mark::Dot`）
+    pub mark: Mark,
+    /// 根拠
+    pub rule: Rule,
+}
+
+/// 途中盤面から論理的に確定する次の 1 手を返す
+///
+/// 規則は「数字の充足」「数字の残り全部」「候補が 1 つ」「セグメントのあかり」の順に調べ，
+/// 最初に見つかったものを返す．どの規則も適用できなければ `None` を返す．
+/// 途中盤面がすでに矛盾している場合の結果は保証しない．
+pub fn next_hint(field: &Field, partial: &PartialSolution) -> Option<Hint> {
+    let (h, w) = (field.h, field.w);
+    let is_light =
+        |r: usize, c: usize| field.field[r][c].is_empty() && partial.field[r][c] == Mark::Light;
+
+    let lights = Solution {
+        field: partial
+            .field
+            .iter()
+            .map(|row| row.iter().map(|&mark| mark == Mark::Light).collect())
+            .collect(),
+    };
+    let lit = lit_cells(field, &lights);
+
+    // まだあかりを置く余地のあるセル
+    let is_open = |r: usize, c: usize| {
+        field.field[r][c].is_empty() && partial.field[r][c] == Mark::Unknown && !lit[r][c]
+    };
+
+    // 数字セルごとの (位置, 数字, 隣接するあかりの数, 隣接する置けるセル)
+    let numbers: Vec<_> = (0..h * w)
+        .map(|i| (i / w, i % w))
+        .filter_map(|pos| {
+            let value = field.field[pos.0][pos.1].is_adj()?;
+            let on = pos.adj(h, w).filter(|&(r, c)| is_light(r, c)).count();
+            let open: Vec<_> = pos.adj(h, w).filter(|&(r, c)| is_open(r, c)).collect();
+            Some((pos, value, on, open))
+        })
+        .collect();
+
+    // 数字の周りにすでに必要な数のあかりがある
+    for (number, value, on, open) in &numbers {
+        if let Some(&pos) = open.first()
+            && on == value
+        {
+            return Some(Hint {
+                pos,
+                mark: Mark::Dot,
+                rule: Rule::NumberSatisfied { number: *number },
+            });
+        }
+    }
+
+    // 数字の周りの置けるセルすべてにあかりが必要
+    for (number, value, on, open) in &numbers {
+        if let Some(&pos) = open.first()
+            && on + open.len() == *value
+        {
+            return Some(Hint {
+                pos,
+                mark: Mark::Light,
+                rule: Rule::NumberNeedsAll { number: *number },
+            });
+        }
+    }
+
+    // 照らされていないセルを照らせる候補が 1 つだけ
+    for r in 0..h {
+        for c in 0..w {
+            if !field.field[r][c].is_empty() || lit[r][c] {
+                continue;
+            }
+            let candidates: Vec<_> = illuminated(field, (r, c))
+                .into_iter()
+                .filter(|&(r, c)| is_open(r, c))
+                .collect();
+            if let [pos] = candidates[..] {
+                return Some(Hint {
+                    pos,
+                    mark: Mark::Light,
+                    rule: Rule::SingleCandidate { target: (r, c) },
+                });
+            }
+        }
+    }
+
+    // 照らされているセルにはあかりを置けない
+    for r in 0..h {
+        for c in 0..w {
+            if lit[r][c]
+                && field.field[r][c].is_empty()
+                && partial.field[r][c] == Mark::Unknown
+                && let Some(light) = illuminated(field, (r, c))
+                    .into_iter()
+                    .find(|&(r, c)| is_light(r, c))
+            {
+                return Some(Hint {
+                    pos: (r, c),
+                    mark: Mark::Dot,
+                    rule: Rule::SegmentHasLight { light },
+                });
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test_hint {
+    use crate::{
+        field::{Field, Mark, PartialSolution},
+        hint::{Hint, Rule, next_hint},
+    };
+
+    #[test]
+    fn test_number_rules() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let mut partial = PartialSolution::new(3, 3);
+        assert_eq!(
+            next_hint(&field, &partial),
+            Some(Hint {
+                pos: (1, 2),
+                mark: Mark::Dot,
+                rule: Rule::NumberSatisfied { number: (2, 2) },
+            })
+        );
+
+        partial.field[1][2] = Mark::Dot;
+        partial.field[2][1] = Mark::Dot;
+        assert_eq!(
+            next_hint(&field, &partial),
+            Some(Hint {
+                pos: (0, 1),
+                mark: Mark::Light,
+                rule: Rule::NumberNeedsAll { number: (0, 0) },
+            })
+        );
+    }
+
+    #[test]
+    fn test_single_candidate_and_segment() {
+        let field = Field::from_str(2, 3, "... #..").unwrap();
+        let mut partial = PartialSolution::new(2, 3);
+        partial.field[0][1] = Mark::Dot;
+        partial.field[0][2] = Mark::Dot;
+        assert_eq!(
+            next_hint(&field, &partial),
+            Some(Hint {
+                pos: (0, 0),
+                mark: Mark::Light,
+                rule: Rule::SingleCandidate { target: (0, 0) },
+            })
+        );
+
+        partial.field[0][0] = Mark::Light;
+        partial.field[1][1] = Mark::Light;
+        assert_eq!(
+            next_hint(&field, &partial),
+            Some(Hint {
+                pos: (1, 2),
+                mark: Mark::Dot,
+                rule: Rule::SegmentHasLight { light: (1, 1) },
+            })
+        );
+    }
+}
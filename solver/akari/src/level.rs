@@ -0,0 +1,201 @@
+//! dailyakari の `levelData` の解析
+
+use crate::{
+    error::ParseError,
+    field::{Field, Solution, State},
+};
+
+/// 解答の節であかりを表すトークン
+///
+/// 実際の `levelData` の解答の節からはまだ確認していない．
+/// サイトから取得した `levelData` を試験に加えるまでは，解釈できない節を誤りとせずメタデータとして残す．
+const LIGHT_TOKENS: [&str; 4] = ["A", "L", "*", "@"];
+
+/// dailyakari の `levelData` の内容
+///
+/// `levelData` は空行で区切られた節からなり，最初の節が盤面，
+/// 以降の節には公式の解答やメタデータが入る．
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelData {
+    pub field: Field,
+    /// 解答として解釈できた最初の節
+    pub answer: Option<Solution>,
+    /// 解答として解釈できなかった節（前後の空白を除いたもの）
+    pub metadata: Vec<String>,
+}
+
+impl LevelData {
+    /// `levelData` の文字列を解析する
+    ///
+    /// 盤面の各行はセルを空白区切りで並べたもの（空白を含まない行は 1 文字を 1 セルとする）．
+    /// 解答の節は盤面と同じ大きさで，あかりを `A` `L` `*` `@` のいずれかで表し，
+    /// 空白セル以外にあかりがあるものや，あかりが 1 つもないものは解答とみなさない．
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let text = text.replace("\r\n", "\n");
+        let mut sections = text
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|section| !section.is_empty());
+
+        let field = parse_field(sections.next().ok_or(ParseError::Empty)?)?;
+        let mut answer = None;
+        let mut metadata = Vec::new();
+        for section in sections {
+            match parse_answer(&field, section) {
+                Some(sol) if answer.is_none() => answer = Some(sol),
+                _ => metadata.push(section.to_string()),
+            }
+        }
+
+        Ok(LevelData {
+            field,
+            answer,
+            metadata,
+        })
+    }
+
+    /// 解答との差分（あかりの有無が異なるセル）を返す（解答がなければ `None`）
+    pub fn mismatches(&self, sol: &Solution) -> Option<Vec<(usize, usize)>> {
+        self.answer.as_ref().map(|answer| answer.diff(sol))
+    }
+}
+
+/// 1 行をセルのトークンに分ける
+fn tokens(line: &str) -> Vec<&str> {
+    if line.contains(char::is_whitespace) {
+        line.split_whitespace().collect()
+    } else {
+        line.char_indices()
+            .map(|(i, ch)| &line[i..i + ch.len_utf8()])
+            .collect()
+    }
+}
+
+fn rows(section: &str) -> impl Iterator<Item = Vec<&str>> {
+    section
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(tokens)
+}
+
+fn parse_field(section: &str) -> Result<Field, ParseError> {
+    let mut field: Vec<Vec<State>> = Vec::new();
+    for (row, tokens) in rows(section).enumerate() {
+        let cells = tokens
+            .iter()
+            .enumerate()
+            .map(|(col, &token)| {
+                let mut chars = token.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => {
+                        State::from_char(ch).map_err(|_| ParseError::InvalidChar { row, col, ch })
+                    }
+                    _ => Err(ParseError::InvalidToken {
+                        row,
+                        col,
+                        token: token.to_string(),
+                    }),
+                }
+            })
+            .collect::<Result<Vec<State>, ParseError>>()?;
+        if let Some(first) = field.first()
+            && first.len() != cells.len()
+        {
+            return Err(ParseError::RowWidth {
+                row,
+                expected: first.len(),
+                actual: cells.len(),
+            });
+        }
+        field.push(cells);
+    }
+
+    let h = field.len();
+    let w = field.first().map_or(0, Vec::len);
+    if h == 0 || w == 0 {
+        return Err(ParseError::Empty);
+    }
+    Ok(Field { h, w, field })
+}
+
+fn parse_answer(field: &Field, section: &str) -> Option<Solution> {
+    let rows: Vec<_> = rows(section).collect();
+    if rows.len() != field.h || rows.iter().any(|row| row.len() != field.w) {
+        return None;
+    }
+
+    let mut grid = vec![vec![false; field.w]; field.h];
+    for (r, row) in rows.iter().enumerate() {
+        for (c, token) in row.iter().enumerate() {
+            if LIGHT_TOKENS.contains(token) {
+                if !field.field[r][c].is_empty() {
+                    return None;
+                }
+                grid[r][c] = true;
+            }
+        }
+    }
+    grid.iter()
+        .flatten()
+        .any(|&light| light)
+        .then_some(Solution { field: grid })
+}
+
+#[cfg(test)]
+mod test_level {
+    use crate::{Field, LevelData, ParseError, Solver, solver::Fast};
+
+    #[test]
+    fn parse_board_answer_and_metadata() {
+        let text = ". . 1\n. # .\n1 . .\n\nA . 1\n. # A\n1 A .\n\nid: 42\nsize: 3x3\n";
+        let level = LevelData::parse(text).unwrap();
+        assert_eq!(level.field, Field::from_str(3, 3, "..1 .#. 1..").unwrap());
+        assert_eq!(level.metadata, vec!["id: 42\nsize: 3x3".to_string()]);
+
+        let answer = level.answer.as_ref().unwrap();
+        assert_eq!(answer.akari_indices(), vec![(0, 0), (1, 2), (2, 1)]);
+        let sol = Fast::new().solve(&level.field).unwrap();
+        assert_eq!(level.mismatches(&sol), Some(vec![]));
+
+        // 盤面だけ（数字を含む盤面の写しは解答とみなさない）
+        let level = LevelData::parse("..1\r\n.#.\r\n\r\n..1\r\n.#.\r\n").unwrap();
+        assert_eq!((level.field.h, level.field.w), (2, 3));
+        assert_eq!(level.answer, None);
+        assert_eq!(level.metadata.len(), 1);
+
+        // 解答の形式に合わない 2 番目の節もメタデータとして残す
+        let level = LevelData::parse("..1\n.#.\n\nid: 42\n").unwrap();
+        assert_eq!(level.answer, None);
+        assert_eq!(level.metadata, vec!["id: 42".to_string()]);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(LevelData::parse("\n\n"), Err(ParseError::Empty));
+        assert_eq!(
+            LevelData::parse(". .\n. . .\n"),
+            Err(ParseError::RowWidth {
+                row: 1,
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            LevelData::parse(". 10\n. .\n"),
+            Err(ParseError::InvalidToken {
+                row: 0,
+                col: 1,
+                token: "10".to_string()
+            })
+        );
+        assert_eq!(
+            LevelData::parse(". x\n"),
+            Err(ParseError::InvalidChar {
+                row: 0,
+                col: 1,
+                ch: 'x'
+            })
+        );
+    }
+}
@@ -1,9 +1,39 @@
+mod bitset;
+mod cnf;
+mod error;
 #[allow(clippy::needless_range_loop)]
 mod field;
+#[allow(clippy::needless_range_loop)]
+mod generator;
+#[allow(clippy::needless_range_loop)]
+mod hint;
+mod level;
+mod observer;
+#[cfg(feature = "png")]
+mod png;
 mod progress_bar;
+mod pzpr;
+mod rating;
+mod sat;
+#[cfg(feature = "serde")]
+mod serialize;
 pub mod solver;
+mod svg;
 mod utility;
+#[allow(clippy::needless_range_loop)]
+mod validate;
 
+pub use cnf::{Cnf, parse_model};
+pub use error::ParseError;
 pub use field::*;
+pub use generator::{Generator, Symmetry};
+pub use hint::{Hint, Rule, next_hint};
+pub use level::LevelData;
+pub use observer::{ChannelObserver, Event, NullObserver, Observer, TerminalObserver};
+#[cfg(feature = "png")]
+pub use png::PngRenderer;
 pub use progress_bar::ProgressBar;
+pub use rating::{Difficulty, Rating, Technique, rate};
 pub use solver::Solver;
+pub use svg::SvgRenderer;
+pub use validate::{Violation, validate};
@@ -1,11 +1,15 @@
 use std::io::Read;
 
-use akari::{Field, Solver, solver};
+use akari::{Field, ParseError, Solver, TerminalObserver, solver};
 
-fn main() -> Result<(), &'static str> {
+fn main() -> Result<(), ParseError> {
     let (h, w, s) = get_input();
 
-    let solver = solver::Fast::new();
+    // `--progress` を付けると探索の進捗を標準エラー出力に表示する
+    let mut solver = solver::Fast::new();
+    if std::env::args().any(|arg| arg == "--progress") {
+        solver = solver.with_observer(TerminalObserver::new());
+    }
     let field = Field::from_str(h, w, &s)?;
 
     // 求解
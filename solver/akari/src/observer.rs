@@ -0,0 +1,167 @@
+//! 探索の進捗の通知
+
+use std::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use crate::ProgressBar;
+
+/// ノードを何個展開するごとに端末の表示を更新するか
+const TERMINAL_NODE_INTERVAL: usize = 1 << 12;
+
+/// 探索中に起きた出来事
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// 探索木のノードを展開した（`nodes` はそれまでの累計）
+    NodeExpanded { nodes: usize, depth: usize },
+    /// 数字の制約を満たす配置の列挙が `done` / `total` まで進んだ
+    Enumeration { done: usize, total: usize },
+    /// 解を見つけた
+    SolutionFound,
+    /// 探索を終えた
+    Finished,
+}
+
+/// 探索の進捗を受け取るトレイト
+///
+/// ソルバは `&self` で解くので，状態を持つ場合は内部可変性を使う．
+pub trait Observer: Send + Sync {
+    fn notify(&self, event: Event);
+}
+
+/// 何もしない
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullObserver;
+
+impl Observer for NullObserver {
+    fn notify(&self, _event: Event) {}
+}
+
+/// 標準エラー出力に進捗を表示する
+///
+/// 列挙の進捗は [`ProgressBar`] で，ノードの展開は一定間隔で表示する．
+#[derive(Default)]
+pub struct TerminalObserver {
+    state: Mutex<TerminalState>,
+}
+
+#[derive(Default)]
+struct TerminalState {
+    bar: Option<ProgressBar>,
+    /// ノードの展開の行を表示して，まだ改行していないか
+    node_line: bool,
+}
+
+impl TerminalObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Observer for TerminalObserver {
+    fn notify(&self, event: Event) {
+        let mut state = self.state.lock().unwrap();
+        match event {
+            Event::NodeExpanded { nodes, depth } => {
+                // プログレスバーの表示中はそちらを優先する
+                if state.bar.is_none() && nodes % TERMINAL_NODE_INTERVAL == 0 {
+                    eprint!("\rnodes: {nodes}, depth: {depth}");
+                    state.node_line = true;
+                }
+            }
+            Event::Enumeration { done: 0, total } => {
+                if std::mem::take(&mut state.node_line) {
+                    eprintln!();
+                }
+                state.bar = Some(ProgressBar::new(total));
+            }
+            Event::Enumeration { .. } => {
+                if let Some(bar) = state.bar.as_mut() {
+                    bar.tick();
+                }
+            }
+            Event::SolutionFound => {}
+            Event::Finished => {
+                if let Some(mut bar) = state.bar.take() {
+                    bar.finish();
+                }
+                if std::mem::take(&mut state.node_line) {
+                    eprintln!();
+                }
+            }
+        }
+    }
+}
+
+/// チャネルに出来事を送る（受信側が閉じていれば捨てる）
+#[derive(Clone, Debug)]
+pub struct ChannelObserver {
+    sender: Sender<Event>,
+}
+
+impl ChannelObserver {
+    pub fn new(sender: Sender<Event>) -> Self {
+        Self { sender }
+    }
+
+    /// 送信側と受信側の組を作る
+    pub fn channel() -> (Self, Receiver<Event>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::new(sender), receiver)
+    }
+}
+
+impl Observer for ChannelObserver {
+    fn notify(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod test_observer {
+    use crate::{
+        ChannelObserver, Event, Field, Solver,
+        solver::{CFS, Cdcl, Dlx, Fast, Naive},
+    };
+
+    #[test]
+    fn fast_reports_nodes_and_solution() {
+        let field = Field::from_str(3, 3, "... .#. ...").unwrap();
+        let (observer, events) = ChannelObserver::channel();
+        assert!(Fast::new().with_observer(observer).solve(&field).is_some());
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert_eq!(events[0], Event::NodeExpanded { nodes: 1, depth: 0 });
+        assert!(events.contains(&Event::SolutionFound));
+        assert_eq!(events.last(), Some(&Event::Finished));
+    }
+
+    #[test]
+    fn every_solver_reports_finished() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let (observer, events) = ChannelObserver::channel();
+        let solvers: Vec<Box<dyn Solver>> = vec![
+            Box::new(Naive::new().with_observer(observer.clone())),
+            Box::new(CFS::default().with_observer(observer.clone())),
+            Box::new(Cdcl::new().with_observer(observer.clone())),
+            Box::new(Dlx::new().with_observer(observer.clone())),
+        ];
+        for solver in solvers {
+            assert!(solver.solve(&field).is_some());
+            let events: Vec<_> = events.try_iter().collect();
+            assert!(matches!(events[0], Event::NodeExpanded { nodes: 1, .. }));
+            assert!(events.contains(&Event::SolutionFound));
+            assert_eq!(events.last(), Some(&Event::Finished));
+        }
+
+        // 列挙は終えたとき（途中でやめたときは破棄したとき）に 1 度だけ通知する
+        let fast = Fast::new().with_observer(observer);
+        let empty = Field::from_str(2, 2, "..\n..\n").unwrap();
+        assert_eq!(fast.count_solutions(&empty, 1), 1);
+        assert_eq!(fast.solutions(&empty).count(), 2);
+        fast.solve_with_stats(&field);
+        let finished = events.try_iter().filter(|e| *e == Event::Finished).count();
+        assert_eq!(finished, 3);
+    }
+}
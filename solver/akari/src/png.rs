@@ -0,0 +1,271 @@
+//! PNG による盤面の描画（`png` フィーチャ）
+//!
+//! [`crate::SvgRenderer`] と同じ配色で，外部のツールを使わずに画素を塗る．
+
+use crate::{
+    field::{Field, Solution, State},
+    svg::{BULB_COLOR, GRID_COLOR, HIGHLIGHT_COLOR, LIT_COLOR, NUMBER_COLOR, WALL_COLOR},
+    validate::{Violation, lit_cells},
+};
+
+type Rgb = [u8; 3];
+
+/// 数字の 3x5 のビットマップ（各行の下位 3 ビットを左から使う）
+const DIGITS: [[u8; 5]; 5] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+];
+
+/// 盤面を PNG で描く設定
+#[derive(Debug, Clone, Copy)]
+pub struct PngRenderer {
+    /// 1 セルの一辺の長さ（px，0 なら 1 とみなす）
+    pub cell_size: usize,
+    /// 解があるとき，照らされたセルに色を塗るか
+    pub shade_lit: bool,
+}
+
+impl Default for PngRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PngRenderer {
+    pub fn new() -> Self {
+        Self {
+            cell_size: 32,
+            shade_lit: true,
+        }
+    }
+
+    /// 盤面を描き，PNG のバイト列を返す
+    ///
+    /// 引数の意味は [`crate::SvgRenderer::render`] と同じ．
+    /// PNG は幅と高さが 1 以上でなければならないので，空の盤面は枠だけの 1x1 の画像になる．
+    pub fn render(
+        &self,
+        field: &Field,
+        solution: Option<&Solution>,
+        highlights: &[(usize, usize)],
+    ) -> Vec<u8> {
+        let s = self.cell_size.max(1);
+        let mut canvas = Canvas::new((field.w * s).max(1), (field.h * s).max(1));
+        let lit = solution.map(|sol| lit_cells(field, sol));
+
+        for r in 0..field.h {
+            for c in 0..field.w {
+                let (x, y) = (c * s, r * s);
+                let state = field.field[r][c];
+                match state {
+                    State::Empty if self.shade_lit && lit.as_ref().is_some_and(|lit| lit[r][c]) => {
+                        canvas.fill_rect(x, y, s, s, rgb(LIT_COLOR));
+                    }
+                    State::Empty => {}
+                    _ => canvas.fill_rect(x, y, s, s, rgb(WALL_COLOR)),
+                }
+                canvas.stroke_rect(x, y, s, s, 1, rgb(GRID_COLOR));
+
+                if let Some(n) = state.is_adj() {
+                    canvas.draw_digit(n, x + s / 2, y + s / 2, s * 3 / 5, rgb(NUMBER_COLOR));
+                }
+                if state.is_empty() && solution.is_some_and(|sol| sol.field[r][c]) {
+                    canvas.fill_circle(x + s / 2, y + s / 2, s * 3 / 10, rgb(BULB_COLOR));
+                }
+            }
+        }
+
+        for &(r, c) in highlights {
+            if r < field.h && c < field.w {
+                let color = rgb(HIGHLIGHT_COLOR);
+                canvas.blend_rect(c * s, r * s, s, s, color, 0.4);
+                canvas.stroke_rect(c * s, r * s, s, s, 2, color);
+            }
+        }
+
+        canvas.stroke_rect(0, 0, canvas.w, canvas.h, 2, rgb(WALL_COLOR));
+        canvas.encode()
+    }
+
+    /// 解とその違反（[`crate::validate`] の結果）に関係するセルを赤で描く
+    pub fn render_violations(
+        &self,
+        field: &Field,
+        solution: &Solution,
+        violations: &[Violation],
+    ) -> Vec<u8> {
+        let cells: Vec<_> = violations.iter().flat_map(Violation::cells).collect();
+        self.render(field, Some(solution), &cells)
+    }
+}
+
+/// `#rrggbb` 形式の色
+fn rgb(hex: &str) -> Rgb {
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    [channel(1), channel(3), channel(5)]
+}
+
+/// RGB の画素の並び
+struct Canvas {
+    w: usize,
+    h: usize,
+    data: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            data: vec![0xff; w * h * 3],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.w && y < self.h {
+            let i = (y * self.w + x) * 3;
+            self.data[i..i + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: Rgb) {
+        for py in y..y + h {
+            for px in x..x + w {
+                self.set(px, py, color);
+            }
+        }
+    }
+
+    /// 内側に幅 `width` の枠を描く
+    fn stroke_rect(&mut self, x: usize, y: usize, w: usize, h: usize, width: usize, color: Rgb) {
+        let width = width.min(w).min(h);
+        self.fill_rect(x, y, w, width, color);
+        self.fill_rect(x, y + h - width, w, width, color);
+        self.fill_rect(x, y, width, h, color);
+        self.fill_rect(x + w - width, y, width, h, color);
+    }
+
+    /// 不透明度 `alpha` で色を重ねる
+    fn blend_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: Rgb, alpha: f64) {
+        for py in y..(y + h).min(self.h) {
+            for px in x..(x + w).min(self.w) {
+                let i = (py * self.w + px) * 3;
+                for (dst, &src) in self.data[i..i + 3].iter_mut().zip(&color) {
+                    *dst = (*dst as f64 * (1.0 - alpha) + src as f64 * alpha).round() as u8;
+                }
+            }
+        }
+    }
+
+    /// 縁を壁の色にした円を描く
+    fn fill_circle(&mut self, cx: usize, cy: usize, r: usize, color: Rgb) {
+        let (cx, cy, r) = (cx as isize, cy as isize, r as isize);
+        let border = (r - 1).max(0).pow(2);
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let d = dx * dx + dy * dy;
+                if d <= r * r {
+                    let color = if d > border { rgb(WALL_COLOR) } else { color };
+                    self.set((cx + dx) as usize, (cy + dy) as usize, color);
+                }
+            }
+        }
+    }
+
+    /// 高さがおよそ `size` の数字を中心に描く
+    fn draw_digit(&mut self, n: usize, cx: usize, cy: usize, size: usize, color: Rgb) {
+        let scale = (size / 5).max(1);
+        let (x0, y0) = (
+            cx.saturating_sub(3 * scale / 2),
+            cy.saturating_sub(5 * scale / 2),
+        );
+        for (row, bits) in DIGITS[n].iter().enumerate() {
+            for col in 0..3 {
+                if bits >> (2 - col) & 1 == 1 {
+                    self.fill_rect(x0 + col * scale, y0 + row * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.w as u32, self.h as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.data))
+            .expect("image size must be positive");
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test_png {
+    use crate::{Field, PngRenderer, Solver, solver::Fast, validate};
+
+    /// PNG を読み込み，(幅，高さ，画素) を返す
+    fn decode(bytes: &[u8]) -> (usize, usize, Vec<u8>) {
+        let decoder = png::Decoder::new(bytes);
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        (info.width as usize, info.height as usize, data)
+    }
+
+    #[test]
+    fn rasterizes_board_and_solution() {
+        let field = Field::from_str(2, 3, ".1# ...").unwrap();
+        let sol = Fast::new().solve(&field).unwrap();
+        let renderer = PngRenderer::new();
+
+        let (w, h, data) = decode(&renderer.render(&field, Some(&sol), &[]));
+        assert_eq!((w, h), (96, 64));
+        let pixel = |x: usize, y: usize| {
+            let i = (y * w + x) * 3;
+            [data[i], data[i + 1], data[i + 2]]
+        };
+        // 数字のない壁，数字の画（中央の縦線），あかり，照らされたセル
+        assert_eq!(pixel(80, 16), [0x22, 0x22, 0x22]);
+        assert_eq!(pixel(48, 16), [0xff, 0xff, 0xff]);
+        for (r, c) in sol.akari_indices() {
+            assert_eq!(pixel(c * 32 + 16, r * 32 + 16), [0xff, 0xb3, 0x00]);
+        }
+        let (r, c) = [(1, 0), (1, 1), (1, 2)]
+            .into_iter()
+            .find(|&(r, c)| !sol.field[r][c])
+            .unwrap();
+        assert_eq!(pixel(c * 32 + 16, r * 32 + 16), [0xff, 0xf3, 0xb0]);
+
+        let mut bad = sol.clone();
+        bad.field[1] = vec![true, true, true];
+        let violations = validate(&field, &bad);
+        let (_, _, data) = decode(&renderer.render_violations(&field, &bad, &violations));
+        let (r, c) = violations[0].cells()[0];
+        let i = ((r * 32 + 4) * w + c * 32 + 4) * 3;
+        assert!(data[i] > data[i + 1] && data[i] > data[i + 2]);
+    }
+
+    #[test]
+    fn degenerate_sizes_do_not_panic() {
+        let field = Field::from_str(1, 2, "..").unwrap();
+        let renderer = PngRenderer {
+            cell_size: 0,
+            ..PngRenderer::new()
+        };
+        let sol = Fast::new().solve(&field).unwrap();
+        assert_eq!(decode(&renderer.render(&field, Some(&sol), &[(0, 0)])).0, 2);
+
+        let empty = Field {
+            h: 0,
+            w: 0,
+            field: vec![],
+        };
+        let (w, h, _) = decode(&PngRenderer::new().render(&empty, None, &[]));
+        assert_eq!((w, h), (1, 1));
+    }
+}
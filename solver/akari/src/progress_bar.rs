@@ -1,6 +1,6 @@
-use std::io::{Write, stdout};
+use std::io::{Write, stderr};
 
-/// Simple terminal progress bar for enumerating constraint completions.
+/// Simple terminal progress bar for enumerating constraint completions, drawn on stderr.
 pub struct ProgressBar {
     total: usize,
     current: usize,
@@ -37,7 +37,7 @@ impl ProgressBar {
         }
         self.current = self.total;
         self.render();
-        println!();
+        eprintln!();
     }
 
     fn render(&self) {
@@ -48,7 +48,7 @@ impl ProgressBar {
         let filled = ((ratio * self.width as f32).round() as usize).min(self.width);
         let empty = self.width - filled;
         let bar = format!("{}{}", "=".repeat(filled), " ".repeat(empty));
-        print!("\rProgress: [{bar}] {}/{}", self.current, self.total);
-        let _ = stdout().flush();
+        eprint!("\rProgress: [{bar}] {}/{}", self.current, self.total);
+        let _ = stderr().flush();
     }
 }
@@ -0,0 +1,297 @@
+//! puzz.link（pzprjs）の URL 形式と pzprv3 ファイル形式の入出力
+
+use crate::{
+    error::ParseError,
+    field::{Field, Mark, PartialSolution, State},
+};
+
+/// URL で使うパズルの種類の名前（先頭が出力に使うもの）
+const PUZZLE_NAMES: [&str; 2] = ["lightup", "akari"];
+
+/// 読み込む盤面の縦横それぞれの上限
+///
+/// 大きさは盤面より先に書かれるので，セルを確保する前に確かめて巨大な確保を防ぐ．
+const MAX_SIDE: usize = 256;
+
+/// 盤面の大きさを確かめる
+fn check_size(h: usize, w: usize) -> Result<(), ParseError> {
+    if h == 0 || w == 0 {
+        return Err(ParseError::Empty);
+    }
+    if h > MAX_SIDE || w > MAX_SIDE {
+        return Err(ParseError::TooLarge {
+            h,
+            w,
+            max: MAX_SIDE,
+        });
+    }
+    Ok(())
+}
+
+impl Field {
+    /// puzz.link の URL（`https://puzz.link/p?lightup/{列数}/{行数}/{盤面}`）から盤面を作る
+    ///
+    /// `?` より後だけを見るので，pzv.jp などの URL や `lightup/...` だけでも読める．
+    /// 種類の名前は `lightup` と `akari` を受け付ける．縦横それぞれ 256 を超える盤面は読まない．
+    pub fn from_puzz_link(url: &str) -> Result<Field, ParseError> {
+        let invalid = || ParseError::InvalidUrl(url.to_string());
+        let query = url.rsplit_once('?').map_or(url, |(_, query)| query);
+        let mut parts = query.split('/');
+        if !parts
+            .next()
+            .is_some_and(|name| PUZZLE_NAMES.contains(&name))
+        {
+            return Err(invalid());
+        }
+        let w: usize = parts
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        let h: usize = parts
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        check_size(h, w)?;
+        let body = parts.next().unwrap_or("");
+
+        // 1 文字で数字と後続の空白セル，数字のない壁，空白セルの連続のいずれかを表す
+        let mut cells = vec![State::Empty; h * w];
+        let mut i = 0;
+        for ch in body.chars() {
+            if i >= cells.len() {
+                break;
+            }
+            match ch {
+                '.' => {
+                    cells[i] = State::Nil;
+                    i += 1;
+                }
+                '0'..='9' | 'a'..='e' => {
+                    let digit = ch.to_digit(16).unwrap() as usize;
+                    cells[i] = State::from_adj(digit % 5).unwrap();
+                    i += 1 + digit / 5;
+                }
+                'g'..='z' => i += ch.to_digit(36).unwrap() as usize - 15,
+                _ => return Err(invalid()),
+            }
+        }
+
+        let field = cells.chunks(w).map(<[State]>::to_vec).collect();
+        Ok(Field { h, w, field })
+    }
+
+    /// puzz.link の URL に変換する
+    pub fn to_puzz_link(&self) -> String {
+        let cells: Vec<State> = self.field.iter().flatten().copied().collect();
+        let mut body = String::new();
+        let mut run = 0;
+        let mut i = 0;
+        while i < cells.len() {
+            let token = match cells[i] {
+                State::Empty => {
+                    run += 1;
+                    i += 1;
+                    if run == 20 {
+                        body.push(char::from_digit(15 + run, 36).unwrap());
+                        run = 0;
+                    }
+                    continue;
+                }
+                State::Nil => {
+                    i += 1;
+                    '.'
+                }
+                state => {
+                    // 後続の空白セルを 2 つまでまとめる
+                    let value = state.is_adj().unwrap() as u32;
+                    let skip = cells[i + 1..]
+                        .iter()
+                        .take(2)
+                        .take_while(|s| s.is_empty())
+                        .count();
+                    i += 1 + skip;
+                    char::from_digit(value + 5 * skip as u32, 16).unwrap()
+                }
+            };
+            if run > 0 {
+                body.push(char::from_digit(15 + run, 36).unwrap());
+                run = 0;
+            }
+            body.push(token);
+        }
+        if run > 0 {
+            body.push(char::from_digit(15 + run, 36).unwrap());
+        }
+
+        format!(
+            "https://puzz.link/p?{}/{}/{}/{}",
+            PUZZLE_NAMES[0], self.w, self.h, body
+        )
+    }
+
+    /// pzprv3 ファイルから盤面と途中盤面を作る
+    ///
+    /// セルは `-`（数字のない壁），`0` - `4`（数字），`#`（あかり），`+`（あかりを置かない印），
+    /// `.`（空白セル）で，空白区切りで並ぶ．盤面の後に続く行は無視する．
+    /// 縦横それぞれ 256 を超える盤面は読まない．
+    pub fn from_pzprv3(text: &str) -> Result<(Field, PartialSolution), ParseError> {
+        let mut lines = text.lines().map(str::trim);
+        let header = |line: usize, ok: bool| {
+            if ok {
+                Ok(())
+            } else {
+                Err(ParseError::InvalidHeader { line })
+            }
+        };
+        header(0, lines.next() == Some("pzprv3"))?;
+        header(1, lines.next().is_some_and(|s| PUZZLE_NAMES.contains(&s)))?;
+        let h: usize = lines
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or(ParseError::InvalidHeader { line: 2 })?;
+        let w: usize = lines
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or(ParseError::InvalidHeader { line: 3 })?;
+        check_size(h, w)?;
+
+        let mut field = Vec::with_capacity(h);
+        let mut partial = PartialSolution::new(h, w);
+        for row in 0..h {
+            let tokens: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+            if tokens.len() != w {
+                return Err(if tokens.is_empty() {
+                    ParseError::RowCount {
+                        expected: h,
+                        actual: row,
+                    }
+                } else {
+                    ParseError::RowWidth {
+                        row,
+                        expected: w,
+                        actual: tokens.len(),
+                    }
+                });
+            }
+
+            let mut cells = Vec::with_capacity(w);
+            for (col, &token) in tokens.iter().enumerate() {
+                let (state, mark) = match token {
+                    "-" => (State::Nil, Mark::Unknown),
+                    "." => (State::Empty, Mark::Unknown),
+                    "#" => (State::Empty, Mark::Light),
+                    "+" => (State::Empty, Mark::Dot),
+                    _ => {
+                        let state =
+                            token
+                                .parse()
+                                .ok()
+                                .and_then(State::from_adj)
+                                .ok_or_else(|| ParseError::InvalidToken {
+                                    row,
+                                    col,
+                                    token: token.to_string(),
+                                })?;
+                        (state, Mark::Unknown)
+                    }
+                };
+                cells.push(state);
+                partial.field[row][col] = mark;
+            }
+            field.push(cells);
+        }
+
+        Ok((Field { h, w, field }, partial))
+    }
+
+    /// 途中盤面の印とともに pzprv3 ファイルに変換する
+    pub fn to_pzprv3(&self, partial: &PartialSolution) -> String {
+        let mut s = format!("pzprv3\n{}\n{}\n{}\n", PUZZLE_NAMES[0], self.h, self.w);
+        for (states, marks) in self.field.iter().zip(&partial.field) {
+            for (state, mark) in states.iter().zip(marks) {
+                let token = match (state, mark) {
+                    (State::Nil, _) => "-".to_string(),
+                    (State::Empty, Mark::Unknown) => ".".to_string(),
+                    (State::Empty, Mark::Light) => "#".to_string(),
+                    (State::Empty, Mark::Dot) => "+".to_string(),
+                    (state, _) => state.is_adj().unwrap().to_string(),
+                };
+                s += &token;
+                s.push(' ');
+            }
+            s.push('\n');
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test_pzpr {
+    use crate::{Field, Mark, ParseError, PartialSolution};
+
+    #[test]
+    fn puzz_link_round_trip() {
+        let field = Field::from_str(3, 4, "1... #..0 ..2.").unwrap();
+        let url = field.to_puzz_link();
+        assert_eq!(url, "https://puzz.link/p?lightup/4/3/bg.ha7");
+        assert_eq!(Field::from_puzz_link(&url), Ok(field));
+
+        // 空白セルの連続は 20 個ずつ区切る
+        let field = Field::from_str(1, 23, "......................#").unwrap();
+        let url = field.to_puzz_link();
+        assert!(url.ends_with("/23/1/zh."));
+        assert_eq!(Field::from_puzz_link(&url), Ok(field));
+
+        let field = Field::from_puzz_link("http://pzv.jp/p.html?akari/3/2/5.g").unwrap();
+        assert_eq!(field, Field::from_str(2, 3, "0.# ...").unwrap());
+        assert_eq!(
+            Field::from_puzz_link("https://puzz.link/p?nurikabe/3/3/"),
+            Err(ParseError::InvalidUrl(
+                "https://puzz.link/p?nurikabe/3/3/".to_string()
+            ))
+        );
+
+        // 大きさはセルを確保する前に確かめる
+        assert_eq!(
+            Field::from_puzz_link("https://puzz.link/p?lightup/99999/99999/"),
+            Err(ParseError::TooLarge {
+                h: 99999,
+                w: 99999,
+                max: 256
+            })
+        );
+        assert!(Field::from_puzz_link("lightup/256/1/").is_ok());
+        assert_eq!(
+            Field::from_pzprv3("pzprv3\nlightup\n1\n1000000000\n"),
+            Err(ParseError::TooLarge {
+                h: 1,
+                w: 1000000000,
+                max: 256
+            })
+        );
+    }
+
+    #[test]
+    fn pzprv3_round_trip() {
+        let text = "pzprv3\nlightup\n2\n3\n# 1 - \n+ . 4 \n";
+        let (field, partial) = Field::from_pzprv3(text).unwrap();
+        assert_eq!(field, Field::from_str(2, 3, ".1# ..4").unwrap());
+        let mut expected = PartialSolution::new(2, 3);
+        expected.field[0][0] = Mark::Light;
+        expected.field[1][0] = Mark::Dot;
+        assert_eq!(partial, expected);
+        assert_eq!(field.to_pzprv3(&partial), text);
+
+        assert_eq!(
+            Field::from_pzprv3("pzprv3\nlightup\n2\nx\n"),
+            Err(ParseError::InvalidHeader { line: 3 })
+        );
+        assert_eq!(
+            Field::from_pzprv3("pzprv3\nlightup\n2\n2\n. . \n"),
+            Err(ParseError::RowCount {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+}
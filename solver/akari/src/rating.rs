@@ -0,0 +1,122 @@
+//! 盤面の難易度評価
+
+use crate::{
+    field::Field,
+    solver::{Cause, Fast, SearchStats},
+};
+
+/// 解くのに必要な技法（易しい順）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Technique {
+    /// 数字の充足とセグメントの排他のみ
+    Number,
+    /// 照らせる候補が 1 つしかないセルへの配置
+    SingleCandidate,
+    /// 仮置きによる先読みや背理法
+    Lookahead,
+    /// 先読みでも確定せず，推測による分岐が必要
+    Guess,
+}
+
+impl Technique {
+    /// 技法に対応する難易度
+    pub fn difficulty(&self) -> Difficulty {
+        match self {
+            Self::Number => Difficulty::Easy,
+            Self::SingleCandidate => Difficulty::Medium,
+            Self::Lookahead => Difficulty::Hard,
+            Self::Guess => Difficulty::Expert,
+        }
+    }
+}
+
+/// 難易度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+/// 盤面の評価
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rating {
+    /// 必要な技法のうち最も難しいもの
+    pub technique: Technique,
+    pub difficulty: Difficulty,
+    /// `Fast` による探索の統計
+    pub stats: SearchStats,
+}
+
+/// 盤面を解くのに必要な技法で難易度を評価する
+///
+/// 先読みを有効にした `Fast` が分岐せずに解ければ，使った規則のうち最も難しいものを技法とする．
+/// 分岐が必要なら推測（`Technique::Guess`）が必要とみなす．
+/// 解が存在しない場合は `None` を返す．
+pub fn rate(field: &Field) -> Option<Rating> {
+    let solver = Fast::new().with_lookahead(true);
+    let (solution, trace, stats) = solver.solve_traced(field, true);
+    solution?;
+
+    let technique = if stats.branches > 0 {
+        Technique::Guess
+    } else {
+        trace
+            .iter()
+            .map(|step| match step.cause {
+                Cause::SingleCandidate { .. } => Technique::SingleCandidate,
+                Cause::Probe => Technique::Lookahead,
+                _ => Technique::Number,
+            })
+            .max()
+            .unwrap_or(Technique::Number)
+    };
+
+    Some(Rating {
+        technique,
+        difficulty: technique.difficulty(),
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod test_rating {
+    use crate::{
+        field::Field,
+        rating::{Difficulty, Technique, rate},
+        solver::Fast,
+    };
+
+    #[test]
+    fn test_rate() {
+        let field = Field::from_str(1, 3, ".2.").unwrap();
+        let rating = rate(&field).unwrap();
+        assert_eq!(rating.technique, Technique::Number);
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+        assert_eq!(rating.stats.branches, 0);
+
+        let field = Field::from_str(1, 1, ".").unwrap();
+        let rating = rate(&field).unwrap();
+        assert_eq!(rating.technique, Technique::SingleCandidate);
+        assert_eq!(rating.difficulty, Difficulty::Medium);
+
+        let field = Field::from_str(4, 4, "0... #.1. 1... #...").unwrap();
+        let rating = rate(&field).unwrap();
+        assert_eq!(rating.technique, Technique::Lookahead);
+        assert_eq!(rating.difficulty, Difficulty::Hard);
+        assert_eq!(rating.stats.branches, 0);
+
+        let field = Field::from_str(2, 2, "..\n..\n").unwrap();
+        let rating = rate(&field).unwrap();
+        assert_eq!(rating.technique, Technique::Guess);
+        assert_eq!(rating.difficulty, Difficulty::Expert);
+        assert_eq!(rating.stats.branches, 1);
+        assert_eq!(rating.stats.max_depth, 1);
+        let (_, stats) = Fast::new().with_lookahead(true).solve_with_stats(&field);
+        assert_eq!(rating.stats.nodes, stats.nodes);
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        assert_eq!(rate(&field), None);
+    }
+}
@@ -0,0 +1,372 @@
+//! 小さな CDCL SAT ソルバ
+//!
+//! 2-watched literals による単位伝播，1UIP による節学習，
+//! VSIDS による変数選択，Luby 列による再始動を行う．
+
+use crate::solver::Search;
+
+/// リテラル（変数 `v` の正は `2v`，負は `2v + 1`）
+type Lit = usize;
+
+fn lit_of(dimacs: i32) -> Lit {
+    let v = dimacs.unsigned_abs() as usize - 1;
+    2 * v + (dimacs < 0) as usize
+}
+
+fn var_of(lit: Lit) -> usize {
+    lit / 2
+}
+
+/// 再始動の間隔の単位（衝突数）
+const RESTART_BASE: usize = 100;
+const VAR_DECAY: f64 = 0.95;
+
+/// SAT ソルバの結果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SatResult {
+    /// 充足割り当て（DIMACS 形式のリテラル）
+    Sat(Vec<i32>),
+    Unsat,
+    Interrupted,
+}
+
+/// CDCL ソルバ
+#[derive(Clone, Debug)]
+pub(crate) struct Sat {
+    num_vars: usize,
+    clauses: Vec<Vec<Lit>>,
+    /// `watches[l]` は `l` を監視している節（`l` が偽になったら調べる）
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    /// 前回割り当てた値（phase saving）
+    phase: Vec<bool>,
+    /// 空節が追加されたか，レベル 0 で矛盾した
+    unsat: bool,
+}
+
+impl Sat {
+    pub(crate) fn new(num_vars: usize) -> Self {
+        Sat {
+            num_vars,
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * num_vars],
+            assigns: vec![None; num_vars],
+            level: vec![0; num_vars],
+            reason: vec![None; num_vars],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            activity: vec![0.0; num_vars],
+            var_inc: 1.0,
+            phase: vec![false; num_vars],
+            unsat: false,
+        }
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.assigns[var_of(lit)].map(|b| b != (lit & 1 == 1))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// 節（DIMACS 形式のリテラル）を追加する
+    pub(crate) fn add_clause(&mut self, clause: &[i32]) {
+        let mut lits: Vec<Lit> = clause.iter().map(|&l| lit_of(l)).collect();
+        lits.sort_unstable();
+        lits.dedup();
+        // 恒真な節は無視
+        if lits.windows(2).any(|w| w[0] ^ 1 == w[1]) {
+            return;
+        }
+        match lits.len() {
+            0 => self.unsat = true,
+            1 => match self.value(lits[0]) {
+                Some(true) => {}
+                Some(false) => self.unsat = true,
+                None => self.enqueue(lits[0], None),
+            },
+            _ => {
+                self.attach(lits);
+            }
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<Lit>) -> usize {
+        let id = self.clauses.len();
+        self.watches[lits[0]].push(id);
+        self.watches[lits[1]].push(id);
+        self.clauses.push(lits);
+        id
+    }
+
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+        let v = var_of(lit);
+        self.assigns[v] = Some(lit & 1 == 0);
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(lit);
+    }
+
+    /// 単位伝播を行い，矛盾した節があれば返す
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = self.trail[self.qhead] ^ 1;
+            self.qhead += 1;
+
+            let mut ws = std::mem::take(&mut self.watches[false_lit]);
+            let mut i = 0;
+            while i < ws.len() {
+                let id = ws[i];
+                // 偽になったリテラルを 2 番目に置く
+                if self.clauses[id][0] == false_lit {
+                    self.clauses[id].swap(0, 1);
+                }
+                let first = self.clauses[id][0];
+                if self.value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                // 新しく監視するリテラルを探す
+                let len = self.clauses[id].len();
+                if let Some(k) = (2..len).find(|&k| self.value(self.clauses[id][k]) != Some(false))
+                {
+                    self.clauses[id].swap(1, k);
+                    let lit = self.clauses[id][1];
+                    self.watches[lit].push(id);
+                    ws.swap_remove(i);
+                    continue;
+                }
+
+                match self.value(first) {
+                    Some(false) => {
+                        self.watches[false_lit] = ws;
+                        self.qhead = self.trail.len();
+                        return Some(id);
+                    }
+                    _ => self.enqueue(first, Some(id)),
+                }
+                i += 1;
+            }
+            self.watches[false_lit] = ws;
+        }
+        None
+    }
+
+    /// 1UIP により学習節とバックジャンプ先のレベルを求める
+    fn analyze(&mut self, mut confl: usize) -> (Vec<Lit>, usize) {
+        let mut seen = vec![false; self.num_vars];
+        let mut learnt = vec![0];
+        let mut counter = 0;
+        let mut idx = self.trail.len();
+        let mut p: Option<Lit> = None;
+
+        loop {
+            let start = if p.is_some() { 1 } else { 0 };
+            for k in start..self.clauses[confl].len() {
+                let q = self.clauses[confl][k];
+                let v = var_of(q);
+                if seen[v] || self.level[v] == 0 {
+                    continue;
+                }
+                seen[v] = true;
+                self.bump(v);
+                if self.level[v] == self.decision_level() {
+                    counter += 1;
+                } else {
+                    learnt.push(q);
+                }
+            }
+
+            // 次に調べるリテラルを割り当ての逆順に探す
+            loop {
+                idx -= 1;
+                if seen[var_of(self.trail[idx])] {
+                    break;
+                }
+            }
+            let lit = self.trail[idx];
+            p = Some(lit);
+            seen[var_of(lit)] = false;
+            counter -= 1;
+            if counter == 0 {
+                learnt[0] = lit ^ 1;
+                break;
+            }
+            confl = self.reason[var_of(lit)].unwrap();
+        }
+
+        // 2 番目に大きいレベルのリテラルを 2 番目に置く
+        let mut back = 0;
+        if learnt.len() > 1 {
+            let k = (1..learnt.len())
+                .max_by_key(|&k| self.level[var_of(learnt[k])])
+                .unwrap();
+            learnt.swap(1, k);
+            back = self.level[var_of(learnt[1])];
+        }
+        (learnt, back)
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+    }
+
+    fn cancel_until(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let lim = self.trail_lim[level];
+        for &lit in &self.trail[lim..] {
+            let v = var_of(lit);
+            self.phase[v] = lit & 1 == 0;
+            self.assigns[v] = None;
+            self.reason[v] = None;
+        }
+        self.trail.truncate(lim);
+        self.trail_lim.truncate(level);
+        self.qhead = lim;
+    }
+
+    /// 活動度が最大の未割り当て変数
+    fn pick_branch_var(&self) -> Option<usize> {
+        (0..self.num_vars)
+            .filter(|&v| self.assigns[v].is_none())
+            .max_by(|&a, &b| self.activity[a].total_cmp(&self.activity[b]))
+    }
+
+    /// 充足割り当てを求める
+    pub(crate) fn solve(&mut self, search: &Search) -> SatResult {
+        if self.unsat || self.propagate().is_some() {
+            self.unsat = true;
+            return SatResult::Unsat;
+        }
+
+        let mut restart = 0;
+        let mut budget = luby(restart) * RESTART_BASE;
+        loop {
+            if search.expand(self.decision_level()) {
+                self.cancel_until(0);
+                return SatResult::Interrupted;
+            }
+            if let Some(confl) = self.propagate() {
+                if self.decision_level() == 0 {
+                    self.unsat = true;
+                    return SatResult::Unsat;
+                }
+                let (learnt, back) = self.analyze(confl);
+                self.cancel_until(back);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let lit = learnt[0];
+                    let id = self.attach(learnt);
+                    self.enqueue(lit, Some(id));
+                }
+                self.var_inc /= VAR_DECAY;
+
+                budget = budget.saturating_sub(1);
+                if budget == 0 {
+                    restart += 1;
+                    budget = luby(restart) * RESTART_BASE;
+                    self.cancel_until(0);
+                }
+                continue;
+            }
+
+            let Some(v) = self.pick_branch_var() else {
+                let model = (0..self.num_vars)
+                    .map(|v| {
+                        let lit = v as i32 + 1;
+                        if self.assigns[v] == Some(true) {
+                            lit
+                        } else {
+                            -lit
+                        }
+                    })
+                    .collect();
+                return SatResult::Sat(model);
+            };
+            self.trail_lim.push(self.trail.len());
+            let lit = 2 * v + (!self.phase[v]) as usize;
+            self.enqueue(lit, None);
+        }
+    }
+}
+
+/// Luby 列の `i` 項目（0 始まり）: 1, 1, 2, 1, 1, 2, 4, ...
+fn luby(mut i: usize) -> usize {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+#[cfg(test)]
+mod test_sat {
+    use super::{Sat, SatResult, luby};
+    use crate::{
+        NullObserver,
+        solver::{Cancel, Search},
+    };
+
+    #[test]
+    fn luby_sequence() {
+        let seq: Vec<_> = (0..15).map(luby).collect();
+        assert_eq!(seq, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn solve_small_formulas() {
+        // 4 羽の鳩を 3 つの巣に入れる
+        let (p, h) = (4, 3);
+        let var = |i: usize, j: usize| (i * h + j + 1) as i32;
+        let mut sat = Sat::new(p * h);
+        for i in 0..p {
+            sat.add_clause(&(0..h).map(|j| var(i, j)).collect::<Vec<_>>());
+        }
+        for j in 0..h {
+            for a in 0..p {
+                for b in a + 1..p {
+                    sat.add_clause(&[-var(a, j), -var(b, j)]);
+                }
+            }
+        }
+        assert_eq!(
+            sat.solve(&Search::new(&Cancel::new(), &NullObserver)),
+            SatResult::Unsat
+        );
+
+        let mut sat = Sat::new(3);
+        for clause in [&[1, 2][..], &[-1, 3], &[-2, -3], &[-3]] {
+            sat.add_clause(clause);
+        }
+        assert_eq!(
+            sat.solve(&Search::new(&Cancel::new(), &NullObserver)),
+            SatResult::Sat(vec![-1, 2, -3])
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! serde による直列化（`serde` フィーチャ）
+//!
+//! JSON での表現は次のとおり．
+//!
+//! - [`State`]: 1 文字の文字列（`"#"`，`"."`，`"0"` - `"4"`）
+//! - [`Field`]: 行ごとの文字列の配列（`["..1", "#.0"]`）
+//! - [`Solution`]: 大きさとあかりの位置（`{"h": 2, "w": 3, "lights": [[0, 1]]}`）．
+//!   読み込みでは真偽値の 2 次元配列（`[[false, true, false], [false, false, false]]`）も受け付ける．
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::field::{Field, Solution, State};
+
+impl Serialize for State {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(self.to_char())
+    }
+}
+
+impl<'de> Deserialize<'de> for State {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ch = char::deserialize(deserializer)?;
+        State::from_char(ch).map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.field
+                .iter()
+                .map(|row| row.iter().map(State::to_char).collect::<String>()),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        let w = rows.first().map_or(0, |row| row.chars().count());
+        Field::from_str(rows.len(), w, &rows.join("\n")).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum SolutionRepr {
+    Lights {
+        h: usize,
+        w: usize,
+        lights: Vec<(usize, usize)>,
+    },
+    Grid(Vec<Vec<bool>>),
+}
+
+impl Serialize for Solution {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SolutionRepr::Lights {
+            h: self.field.len(),
+            w: self.field.first().map_or(0, Vec::len),
+            lights: self.akari_indices(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Solution {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match SolutionRepr::deserialize(deserializer)? {
+            SolutionRepr::Lights { h, w, lights } => {
+                let mut field = vec![vec![false; w]; h];
+                for (r, c) in lights {
+                    if r >= h || c >= w {
+                        return Err(de::Error::custom(format!(
+                            "light ({r}, {c}) is out of the {h}x{w} board"
+                        )));
+                    }
+                    field[r][c] = true;
+                }
+                Ok(Solution { field })
+            }
+            SolutionRepr::Grid(field) => {
+                let w = field.first().map_or(0, Vec::len);
+                if field.iter().any(|row| row.len() != w) {
+                    return Err(de::Error::custom("rows of the grid differ in width"));
+                }
+                Ok(Solution { field })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_serialize {
+    use serde_json::json;
+
+    use crate::{Field, Solution, State};
+
+    #[test]
+    fn field_round_trip() {
+        let field = Field::from_str(2, 3, "..1 #.0").unwrap();
+        let value = serde_json::to_value(&field).unwrap();
+        assert_eq!(value, json!(["..1", "#.0"]));
+        assert_eq!(serde_json::from_value::<Field>(value).unwrap(), field);
+
+        assert_eq!(serde_json::to_value(State::Adj3).unwrap(), json!("3"));
+        assert!(serde_json::from_value::<Field>(json!(["..", "..."])).is_err());
+        assert!(serde_json::from_value::<Field>(json!(["x"])).is_err());
+    }
+
+    #[test]
+    fn solution_round_trip() {
+        let sol = Solution {
+            field: vec![vec![false, true, false], vec![true, false, false]],
+        };
+        let value = serde_json::to_value(&sol).unwrap();
+        assert_eq!(value, json!({"h": 2, "w": 3, "lights": [[0, 1], [1, 0]]}));
+        assert_eq!(serde_json::from_value::<Solution>(value).unwrap(), sol);
+
+        let grid = json!([[false, true, false], [true, false, false]]);
+        assert_eq!(serde_json::from_value::<Solution>(grid).unwrap(), sol);
+        let out = json!({"h": 1, "w": 1, "lights": [[0, 1]]});
+        assert!(serde_json::from_value::<Solution>(out).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+//! CNF 符号化と CDCL SAT ソルバによる求解
+
+use std::{fmt, sync::Arc};
+
+use crate::{
+    NullObserver, Observer,
+    cnf::Cnf,
+    field::Field,
+    sat::{Sat, SatResult},
+    solver::{Cancel, Search, SolveResult, Solver},
+};
+
+/// 盤面を CNF に変換し，節学習つきの SAT ソルバで解く
+///
+/// [`Fast`](super::Fast) と違い学習した節で探索を枝刈りするため，
+/// 数字の少ない大きな盤面でも時間が安定する．
+#[derive(Clone)]
+pub struct Cdcl {
+    /// 探索の進捗の通知先（決定レベルを深さとして，決定と衝突ごとに通知する）
+    observer: Arc<dyn Observer>,
+}
+
+impl Default for Cdcl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Cdcl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cdcl").finish_non_exhaustive()
+    }
+}
+
+impl Cdcl {
+    pub fn new() -> Self {
+        Self {
+            observer: Arc::new(NullObserver),
+        }
+    }
+
+    /// 探索の進捗を `observer` に通知する
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+}
+
+impl Solver for Cdcl {
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
+        let cnf = Cnf::encode(field);
+        let mut sat = Sat::new(cnf.num_vars);
+        for clause in &cnf.clauses {
+            sat.add_clause(clause);
+        }
+        let search = Search::new(cancel, &*self.observer);
+        let result = match sat.solve(&search) {
+            SatResult::Sat(model) => SolveResult::Solved(cnf.decode(&model)),
+            SatResult::Unsat => SolveResult::Unsolvable,
+            SatResult::Interrupted => SolveResult::Interrupted,
+        };
+        search.finished(matches!(result, SolveResult::Solved(_)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cdcl;
+    use crate::{Field, Generator, Solver, solver::Fast, validate};
+
+    #[test]
+    fn agrees_with_fast() {
+        for (h, w, s) in [(3, 3, "2.1 ... ..0"), (1, 3, ".4."), (3, 3, "0.0 ### 1..")] {
+            let field = Field::from_str(h, w, s).unwrap();
+            let sol = Cdcl::new().solve(&field);
+            assert_eq!(sol.is_some(), Fast::new().solve(&field).is_some());
+            if let Some(sol) = sol {
+                assert_eq!(validate(&field, &sol), vec![]);
+            }
+        }
+
+        for seed in 0..5 {
+            let field = Generator::new(10, 10).generate(seed);
+            let sol = Cdcl::new().solve(&field).unwrap();
+            assert_eq!(validate(&field, &sol), vec![]);
+        }
+    }
+}
@@ -1,21 +1,25 @@
 //! 制約を優先的に探索
 
-use instant::{Duration, Instant};
+use std::{fmt, sync::Arc};
+
+use instant::Duration;
 use itertools::Itertools;
 
 use crate::{
-    Field, Solution, Solver, State,
-    solver::{Cell, OVERLAP_AKARI, TempFill},
+    Field, NullObserver, Observer, Solution, Solver, State,
+    solver::{Cancel, Cell, OVERLAP_AKARI, Search, SolveResult, TempFill},
     utility::{ADJ, GridUtility},
 };
 
 /// constraint first search
 ///
 /// 影響範囲が狭く強い制約を持つセル（数字セル）が「最も情報量の大きい変数」として優先される変数選択ヒューリスティック．
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone)]
 pub struct CFS {
     /// 制限時間
     timeout: Option<Duration>,
+    /// 探索の進捗の通知先
+    observer: Arc<dyn Observer>,
 }
 
 /// CFS の実行結果
@@ -31,14 +35,29 @@ impl Default for CFS {
     }
 }
 
+impl fmt::Debug for CFS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CFS")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
 impl CFS {
     /// 制限時間（秒）を指定してソルバを構築する
     pub fn new(timeout: Option<u64>) -> Self {
         Self {
             timeout: timeout.map(Duration::from_secs),
+            observer: Arc::new(NullObserver),
         }
     }
 
+    /// 探索の進捗を `observer` に通知する
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
     fn rec(
         field: &Field,
         constraints: &[(usize, usize)],
@@ -46,8 +65,7 @@ impl CFS {
         cell_pos: usize,
         sol: Solution,
         fill: TempFill,
-        start: Instant,
-        timeout: Option<Duration>,
+        search: &Search,
         timed_out: &mut bool,
         found: &mut Option<Solution>,
     ) {
@@ -57,7 +75,7 @@ impl CFS {
             return;
         }
 
-        if Self::timed_out(start, timeout) {
+        if search.expand(cons_pos) {
             *timed_out = true;
             return;
         }
@@ -112,8 +130,7 @@ impl CFS {
                             cell_pos,
                             sol,
                             fill,
-                            start,
-                            timeout,
+                            search,
                             timed_out,
                             found,
                         );
@@ -157,8 +174,7 @@ impl CFS {
                                 cell_pos,
                                 sol,
                                 fill,
-                                start,
-                                timeout,
+                                search,
                                 timed_out,
                                 found,
                             );
@@ -203,8 +219,7 @@ impl CFS {
                                 cell_pos,
                                 sol,
                                 fill,
-                                start,
-                                timeout,
+                                search,
                                 timed_out,
                                 found,
                             );
@@ -248,8 +263,7 @@ impl CFS {
                                 cell_pos,
                                 sol,
                                 fill,
-                                start,
-                                timeout,
+                                search,
                                 timed_out,
                                 found,
                             );
@@ -287,8 +301,7 @@ impl CFS {
                             cell_pos,
                             sol,
                             fill,
-                            start,
-                            timeout,
+                            search,
                             timed_out,
                             found,
                         );
@@ -313,8 +326,7 @@ impl CFS {
                     cell_pos + 1,
                     sol,
                     fill,
-                    start,
-                    timeout,
+                    search,
                     timed_out,
                     found,
                 );
@@ -331,8 +343,7 @@ impl CFS {
             cell_pos + 1,
             sol,
             fill,
-            start,
-            timeout,
+            search,
             timed_out,
             found,
         );
@@ -406,11 +417,18 @@ impl CFS {
         false
     }
 
-    fn timed_out(start: Instant, timeout: Option<Duration>) -> bool {
-        timeout.map_or(false, |t| start.elapsed() >= t)
+    pub fn solve_with_result(&self, field: &Field) -> CfsSolveResult {
+        match self.solve_with(field, &Cancel::new()) {
+            SolveResult::Solved(sol) => CfsSolveResult::Solved(sol),
+            SolveResult::Interrupted => CfsSolveResult::Timeout,
+            SolveResult::Unsolvable => CfsSolveResult::Unsolved,
+        }
     }
+}
 
-    pub fn solve_with_result(&self, field: &Field) -> CfsSolveResult {
+impl Solver for CFS {
+    /// 制限時間が指定されていれば `cancel` の期限と早い方で打ち切る
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
         let h = field.field.len();
         let w = field.field.first().as_ref().map(|r| r.len()).unwrap_or(0);
         let sol = Solution {
@@ -438,7 +456,11 @@ impl CFS {
         let mut found = None;
         let mut timed_out = false;
 
-        let start = Instant::now();
+        let cancel = match self.timeout {
+            Some(timeout) => cancel.clone().with_timeout(timeout),
+            None => cancel.clone(),
+        };
+        let search = Search::new(&cancel, &*self.observer);
         Self::rec(
             field,
             &constraints,
@@ -446,25 +468,16 @@ impl CFS {
             0,
             sol,
             fill,
-            start,
-            self.timeout,
+            &search,
             &mut timed_out,
             &mut found,
         );
+        search.finished(found.is_some());
 
         match (found, timed_out) {
-            (Some(sol), _) => CfsSolveResult::Solved(sol),
-            (None, true) => CfsSolveResult::Timeout,
-            (None, false) => CfsSolveResult::Unsolved,
-        }
-    }
-}
-
-impl Solver for CFS {
-    fn solve(&self, field: &Field) -> Option<Solution> {
-        match self.solve_with_result(field) {
-            CfsSolveResult::Solved(sol) => Some(sol),
-            _ => None,
+            (Some(sol), _) => SolveResult::Solved(sol),
+            (None, true) => SolveResult::Interrupted,
+            (None, false) => SolveResult::Unsolvable,
         }
     }
 }
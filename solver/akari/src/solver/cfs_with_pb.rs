@@ -1,31 +1,56 @@
 //! 制約を優先的に探索（プログレスバー付き）
 
+use std::sync::Arc;
+
 use itertools::Itertools;
 
 use crate::{
-    Field, ProgressBar, Solution, Solver, State,
-    solver::{Cell, OVERLAP_AKARI, TempFill},
+    Event, Field, NullObserver, Observer, Solution, Solver, State,
+    solver::{Cancel, Cell, OVERLAP_AKARI, Search, SolveResult, TempFill},
     utility::{ADJ, GridUtility},
 };
 
 /// constraint first search with progress bar
 ///
 /// 影響範囲が狭く強い制約を持つセル（数字セル）が「最も情報量の大きい変数」として優先される変数選択ヒューリスティック．
-pub struct CFSwithPB;
+/// 進捗は `observer` に通知する（既定では通知しない．端末に表示するには [`crate::TerminalObserver`] を渡す）．
+#[derive(Clone)]
+pub struct CFSwithPB {
+    observer: Arc<dyn Observer>,
+}
+
+impl Default for CFSwithPB {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl CFSwithPB {
+    pub fn new() -> Self {
+        Self {
+            observer: Arc::new(NullObserver),
+        }
+    }
+
+    /// 進捗の通知先を `observer` にする
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
     fn rec(
         field: &Field,
         cell_pos: usize,
         sol: Solution,
         fill: TempFill,
+        search: &Search,
         found: &mut Option<Solution>,
     ) {
         // println!("{}", field.display_with_solution_and_state(&sol, &fill));
 
         let (h, w) = (field.h, field.w);
 
-        if found.is_some() {
+        if found.is_some() || search.expand(cell_pos) {
             return;
         }
 
@@ -49,14 +74,14 @@ impl CFSwithPB {
         if fill[r][c].can_put_akari() {
             // あかりを設置
             if let Ok((sol, fill)) = Self::put_akari(field, r, c, sol.clone(), fill.clone()) {
-                Self::rec(field, cell_pos + 1, sol, fill, found);
+                Self::rec(field, cell_pos + 1, sol, fill, search, found);
             }
         }
 
         // あかりを設置しない
         let mut fill = fill;
         fill[r][c].disable();
-        Self::rec(field, cell_pos + 1, sol, fill, found);
+        Self::rec(field, cell_pos + 1, sol, fill, search, found);
     }
 
     /// 制約を充足する配置を列挙する．
@@ -66,13 +91,14 @@ impl CFSwithPB {
         cons_pos: usize,
         sol: Solution,
         fill: TempFill,
+        search: &Search,
     ) -> Vec<(Solution, TempFill)> {
         if cons_pos == constraints.len() {
             return vec![(sol, fill)];
         }
 
-        // 充足不可能なセルがあればスキップ
-        if Self::has_unfeasible_cell(field, &fill) {
+        // 充足不可能なセルがあるか打ち切られればスキップ
+        if Self::has_unfeasible_cell(field, &fill) || search.cancelled() {
             return vec![];
         }
 
@@ -114,6 +140,7 @@ impl CFSwithPB {
                         cons_pos + 1,
                         sol,
                         fill,
+                        search,
                     ));
                 }
             }
@@ -151,6 +178,7 @@ impl CFSwithPB {
                             cons_pos + 1,
                             sol,
                             fill,
+                            search,
                         ));
                     }
                 }
@@ -189,6 +217,7 @@ impl CFSwithPB {
                             cons_pos + 1,
                             sol,
                             fill,
+                            search,
                         ));
                     }
                 }
@@ -226,6 +255,7 @@ impl CFSwithPB {
                             cons_pos + 1,
                             sol,
                             fill,
+                            search,
                         ));
                     }
                 }
@@ -259,6 +289,7 @@ impl CFSwithPB {
                         cons_pos + 1,
                         sol,
                         fill,
+                        search,
                     ));
                 }
             }
@@ -337,7 +368,7 @@ impl CFSwithPB {
 }
 
 impl Solver for CFSwithPB {
-    fn solve(&self, field: &Field) -> Option<Solution> {
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
         let h = field.field.len();
         let w = field.field.first().as_ref().map(|r| r.len()).unwrap_or(0);
         let sol = Solution {
@@ -363,24 +394,27 @@ impl Solver for CFSwithPB {
             })
             .collect();
         let mut found = None;
-        let enumerated = Self::enum_constraints(field, &constraints, 0, sol, fill);
-        let mut progress = ProgressBar::new(enumerated.len());
-        for (sol, fill) in enumerated {
-            Self::rec(field, 0, sol, fill, &mut found);
-            progress.tick();
-            if found.is_some() {
+        let search = Search::new(cancel, &*self.observer);
+        let enumerated = Self::enum_constraints(field, &constraints, 0, sol, fill, &search);
+        let total = enumerated.len();
+        self.observer.notify(Event::Enumeration { done: 0, total });
+        for (i, (sol, fill)) in enumerated.into_iter().enumerate() {
+            Self::rec(field, 0, sol, fill, &search, &mut found);
+            self.observer
+                .notify(Event::Enumeration { done: i + 1, total });
+            if found.is_some() || search.expired() {
                 break;
             }
         }
-        progress.finish();
 
-        found
+        search.finish(found)
     }
 }
 
 #[cfg(test)]
 mod test_cfs2 {
     use crate::{
+        ChannelObserver, Event,
         field::{Field, Solution},
         solver::{Solver, cfs_with_pb::CFSwithPB},
     };
@@ -391,7 +425,7 @@ mod test_cfs2 {
         let answer = Solution {
             field: vec![vec![true, false, true]],
         };
-        assert_eq!(CFSwithPB.solve(&field), Some(answer));
+        assert_eq!(CFSwithPB::default().solve(&field), Some(answer));
 
         let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
         let answer = Solution {
@@ -401,6 +435,23 @@ mod test_cfs2 {
                 vec![false, false, false],
             ],
         };
-        assert_eq!(CFSwithPB.solve(&field), Some(answer));
+        assert_eq!(CFSwithPB::default().solve(&field), Some(answer));
+    }
+
+    #[test]
+    fn reports_progress_to_observer() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let (observer, events) = ChannelObserver::channel();
+        assert!(
+            CFSwithPB::new()
+                .with_observer(observer)
+                .solve(&field)
+                .is_some()
+        );
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(matches!(events[0], Event::Enumeration { done: 0, .. }));
+        assert!(events.contains(&Event::SolutionFound));
+        assert_eq!(events.last(), Some(&Event::Finished));
     }
 }
@@ -0,0 +1,425 @@
+//! Dancing Links（色つき完全被覆問題）による求解
+//!
+//! 数字と「セグメントにあかりは高々 1 つ」を完全被覆として表し，
+//! 照明の条件は解を見つけた後に確かめる．
+
+use std::{fmt, sync::Arc};
+
+use itertools::Itertools;
+
+use crate::{
+    NullObserver, Observer,
+    field::{Field, Solution},
+    solver::{Cancel, Search, SolveResult, Solver, fast::Core},
+};
+
+/// 数字セルに隣接するセルの色
+const LIGHT: i32 = 1;
+const DARK: i32 = 2;
+
+/// Knuth の Algorithm C（色つきの完全被覆）
+///
+/// 項目 `1..=n_primary` が主項目，それ以降が副項目．
+/// ノード `1..=n_items` は項目の見出しで，選択肢のノードはその後に並ぶ．
+#[derive(Clone, Debug)]
+struct ExactCover {
+    n_primary: usize,
+    llink: Vec<usize>,
+    rlink: Vec<usize>,
+    len: Vec<usize>,
+    /// 項目（見出しとノード），区切りでは `-(選択肢の番号 + 1)`
+    top: Vec<i64>,
+    ulink: Vec<usize>,
+    dlink: Vec<usize>,
+    /// 0 は色なし，負は色が確定済み
+    color: Vec<i32>,
+}
+
+impl ExactCover {
+    fn new(n_primary: usize, n_secondary: usize) -> Self {
+        let n = n_primary + n_secondary;
+        // 主項目の環状リストの先頭は 0，副項目は n + 1
+        let mut llink = vec![0; n + 2];
+        let mut rlink = vec![0; n + 2];
+        for i in 1..=n {
+            llink[i] = i - 1;
+            rlink[i - 1] = i;
+        }
+        llink[n + 1] = n;
+        rlink[n] = n + 1;
+        llink[n_primary + 1] = n + 1;
+        rlink[n + 1] = n_primary + 1;
+        llink[0] = n_primary;
+        rlink[n_primary] = 0;
+
+        // 見出しと最初の区切り（区切りの `top` は 0）
+        let nodes = n + 2;
+        ExactCover {
+            n_primary,
+            llink,
+            rlink,
+            len: vec![0; n + 1],
+            top: vec![0; nodes],
+            ulink: (0..nodes).collect(),
+            dlink: (0..nodes).collect(),
+            color: vec![0; nodes],
+        }
+    }
+
+    /// 選択肢を追加する（項目は 1 始まり，色 0 は色なし）
+    fn add_option(&mut self, items: &[(usize, i32)]) {
+        let spacer = self.top.len() - 1;
+        let first = self.top.len();
+        for &(item, color) in items {
+            let node = self.top.len();
+            self.top.push(item as i64);
+            self.color.push(color);
+            self.ulink.push(self.ulink[item]);
+            self.dlink.push(item);
+            let last = self.ulink[item];
+            self.dlink[last] = node;
+            self.ulink[item] = node;
+            self.len[item] += 1;
+        }
+        self.dlink[spacer] = self.top.len() - 1;
+
+        let option = -(self.top[spacer]) + 1;
+        self.top.push(-option);
+        self.color.push(0);
+        self.ulink.push(first);
+        self.dlink.push(0);
+    }
+
+    /// ノード `x` を含む選択肢の番号
+    fn option_of(&self, mut x: usize) -> usize {
+        while self.top[x] > 0 {
+            x += 1;
+        }
+        (-self.top[x] - 1) as usize
+    }
+
+    fn hide(&mut self, p: usize) {
+        let mut q = p + 1;
+        while q != p {
+            let x = self.top[q];
+            let (u, d) = (self.ulink[q], self.dlink[q]);
+            if x <= 0 {
+                q = u;
+            } else {
+                if self.color[q] >= 0 {
+                    self.dlink[u] = d;
+                    self.ulink[d] = u;
+                    self.len[x as usize] -= 1;
+                }
+                q += 1;
+            }
+        }
+    }
+
+    fn unhide(&mut self, p: usize) {
+        let mut q = p - 1;
+        while q != p {
+            let x = self.top[q];
+            let (u, d) = (self.ulink[q], self.dlink[q]);
+            if x <= 0 {
+                q = d;
+            } else {
+                if self.color[q] >= 0 {
+                    self.dlink[u] = q;
+                    self.ulink[d] = q;
+                    self.len[x as usize] += 1;
+                }
+                q -= 1;
+            }
+        }
+    }
+
+    fn cover(&mut self, i: usize) {
+        let mut p = self.dlink[i];
+        while p != i {
+            self.hide(p);
+            p = self.dlink[p];
+        }
+        let (l, r) = (self.llink[i], self.rlink[i]);
+        self.rlink[l] = r;
+        self.llink[r] = l;
+    }
+
+    fn uncover(&mut self, i: usize) {
+        let (l, r) = (self.llink[i], self.rlink[i]);
+        self.rlink[l] = i;
+        self.llink[r] = i;
+        let mut p = self.ulink[i];
+        while p != i {
+            self.unhide(p);
+            p = self.ulink[p];
+        }
+    }
+
+    fn purify(&mut self, p: usize) {
+        let (c, i) = (self.color[p], self.top[p] as usize);
+        let mut q = self.dlink[i];
+        while q != i {
+            if self.color[q] == c {
+                self.color[q] = -1;
+            } else {
+                self.hide(q);
+            }
+            q = self.dlink[q];
+        }
+    }
+
+    fn unpurify(&mut self, p: usize) {
+        let (c, i) = (self.color[p], self.top[p] as usize);
+        let mut q = self.ulink[i];
+        while q != i {
+            if self.color[q] < 0 {
+                self.color[q] = c;
+            } else {
+                self.unhide(q);
+            }
+            q = self.ulink[q];
+        }
+    }
+
+    fn commit(&mut self, p: usize) {
+        let j = self.top[p] as usize;
+        match self.color[p] {
+            0 => self.cover(j),
+            c if c > 0 => self.purify(p),
+            _ => {}
+        }
+    }
+
+    fn uncommit(&mut self, p: usize) {
+        let j = self.top[p] as usize;
+        match self.color[p] {
+            0 => self.uncover(j),
+            c if c > 0 => self.unpurify(p),
+            _ => {}
+        }
+    }
+
+    /// 解を見つけるたびに `accept` を呼び，`true` が返れば探索を打ち切る
+    fn search(
+        &mut self,
+        chosen: &mut Vec<usize>,
+        search: &Search,
+        accept: &mut impl FnMut(&[usize]) -> bool,
+    ) -> bool {
+        if search.expand(chosen.len()) {
+            return false;
+        }
+        if self.rlink[0] == 0 {
+            return accept(chosen);
+        }
+
+        // 選択肢の最も少ない主項目
+        let mut i = self.rlink[0];
+        let mut p = self.rlink[i];
+        while p != 0 {
+            if self.len[p] < self.len[i] {
+                i = p;
+            }
+            p = self.rlink[p];
+        }
+        debug_assert!(i <= self.n_primary);
+
+        self.cover(i);
+        let mut x = self.dlink[i];
+        let mut found = false;
+        while x != i {
+            let mut p = x + 1;
+            while p != x {
+                if self.top[p] <= 0 {
+                    p = self.ulink[p];
+                } else {
+                    self.commit(p);
+                    p += 1;
+                }
+            }
+
+            chosen.push(self.option_of(x));
+            found = self.search(chosen, search, accept);
+            chosen.pop();
+
+            let mut p = x - 1;
+            while p != x {
+                if self.top[p] <= 0 {
+                    p = self.dlink[p];
+                } else {
+                    self.uncommit(p);
+                    p -= 1;
+                }
+            }
+            if found {
+                break;
+            }
+            x = self.dlink[x];
+        }
+        self.uncover(i);
+        found
+    }
+}
+
+/// 完全被覆問題として解くソルバ
+///
+/// 主項目は数字・行と列のセグメント・数字に隣接するセルで，選択肢は
+/// 「セルにあかりを置く」「セグメントにあかりを置かない」「数字に隣接するセルにあかりを置かない」
+/// 「数字の周りのあかりの置き方」の 4 種類．照らされていないセルのある解は捨てる．
+#[derive(Clone)]
+pub struct Dlx {
+    /// 探索の進捗の通知先
+    observer: Arc<dyn Observer>,
+}
+
+impl Default for Dlx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Dlx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dlx").finish_non_exhaustive()
+    }
+}
+
+impl Dlx {
+    pub fn new() -> Self {
+        Self {
+            observer: Arc::new(NullObserver),
+        }
+    }
+
+    /// 探索の進捗を `observer` に通知する
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+}
+
+impl Solver for Dlx {
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
+        let core = Core::new(field);
+        let n_empty = core.empty_pos().len();
+        let segments: Vec<&[usize]> = core.segments().collect();
+        let numbers: Vec<(usize, &[usize])> = core.numbers().collect();
+
+        // 各セルの属するセグメント（行が先に並ぶ）
+        let mut seg_of = vec![Vec::new(); n_empty];
+        for (s, cells) in segments.iter().enumerate() {
+            for &cell in cells.iter() {
+                seg_of[cell].push(s);
+            }
+        }
+
+        // 項目の番号: 数字，セグメント，数字に隣接するセル（主項目），その色（副項目）
+        let mut adj_id = vec![None; n_empty];
+        let mut n_adj = 0;
+        for &cell in numbers.iter().flat_map(|(_, adj)| adj.iter()) {
+            if adj_id[cell].is_none() {
+                adj_id[cell] = Some(n_adj);
+                n_adj += 1;
+            }
+        }
+        let num_item = |k: usize| 1 + k;
+        let seg_item = |s: usize| 1 + numbers.len() + s;
+        let cell_item = |a: usize| 1 + numbers.len() + segments.len() + a;
+        let color_item = |a: usize| 1 + numbers.len() + segments.len() + n_adj + a;
+
+        let mut cover = ExactCover::new(numbers.len() + segments.len() + n_adj, n_adj);
+        // 選択肢 k があかりを置くセル
+        let mut light_of = Vec::new();
+
+        for cell in 0..n_empty {
+            let mut items = vec![
+                (seg_item(seg_of[cell][0]), 0),
+                (seg_item(seg_of[cell][1]), 0),
+            ];
+            if let Some(a) = adj_id[cell] {
+                items.push((cell_item(a), 0));
+                items.push((color_item(a), LIGHT));
+            }
+            cover.add_option(&items);
+            light_of.push(Some(cell));
+        }
+        for s in 0..segments.len() {
+            cover.add_option(&[(seg_item(s), 0)]);
+            light_of.push(None);
+        }
+        for a in 0..n_adj {
+            cover.add_option(&[(cell_item(a), 0), (color_item(a), DARK)]);
+            light_of.push(None);
+        }
+        for (k, &(value, adj)) in numbers.iter().enumerate() {
+            for on in adj.iter().combinations(value) {
+                let mut items = vec![(num_item(k), 0)];
+                for &cell in adj {
+                    let color = if on.contains(&&cell) { LIGHT } else { DARK };
+                    items.push((color_item(adj_id[cell].unwrap()), color));
+                }
+                cover.add_option(&items);
+                light_of.push(None);
+            }
+        }
+
+        let mut lights = Vec::new();
+        let search = Search::new(cancel, &*self.observer);
+        let found = cover.search(&mut Vec::new(), &search, &mut |chosen| {
+            let cells: Vec<usize> = chosen.iter().filter_map(|&k| light_of[k]).collect();
+            let mut has_light = vec![false; segments.len()];
+            for &cell in &cells {
+                for &s in &seg_of[cell] {
+                    has_light[s] = true;
+                }
+            }
+            // 照らされていないセルがあれば捨てる
+            if (0..n_empty).any(|cell| seg_of[cell].iter().all(|&s| !has_light[s])) {
+                return false;
+            }
+            lights = cells;
+            true
+        });
+        if !found {
+            return search.finish(None);
+        }
+
+        let mut grid = vec![vec![false; field.w]; field.h];
+        for cell in lights {
+            let (r, c) = core.empty_pos()[cell];
+            grid[r][c] = true;
+        }
+        search.finish(Some(Solution { field: grid }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dlx;
+    use crate::{Field, Generator, Solver, solver::Fast, validate};
+
+    #[test]
+    fn agrees_with_fast() {
+        for (h, w, s) in [
+            (3, 3, "2.1 ... ..0"),
+            (1, 3, ".2."),
+            (1, 3, ".4."),
+            (3, 3, "0.0 ### 1.."),
+            (3, 3, "... .#. ..."),
+        ] {
+            let field = Field::from_str(h, w, s).unwrap();
+            let sol = Dlx::new().solve(&field);
+            assert_eq!(sol.is_some(), Fast::new().solve(&field).is_some(), "{}", s);
+            if let Some(sol) = sol {
+                assert_eq!(validate(&field, &sol), vec![]);
+            }
+        }
+
+        for seed in 0..5 {
+            let field = Generator::new(7, 7).generate(seed);
+            let sol = Dlx::new().solve(&field).unwrap();
+            assert_eq!(validate(&field, &sol), vec![]);
+        }
+    }
+}
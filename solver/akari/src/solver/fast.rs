@@ -1,81 +1,777 @@
-//! alg-fast.md に基づく高速ソルバ
+//! 制約伝播と分岐による高速ソルバ（方針は `docs/alg-fast.md` を参照）
 
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
 
-use crate::{utility::GridUtility, Field, Solution, Solver, State};
+use instant::{Duration, Instant};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum CellState {
-    Unknown,
-    Light,
-    Blocked,
-}
-
-#[derive(Clone, Debug)]
-struct Segment {
-    cells: Vec<usize>,
-    light: Option<usize>,
-    free_cnt: i32, // Blocked でないセル数
-}
+use crate::{
+    Event, Field, NullObserver, Observer, Solution, Solver, State,
+    bitset::BitSet,
+    field::{Mark, PartialSolution},
+    solver::{Cancel, SolveResult},
+    utility::GridUtility,
+};
 
 #[derive(Clone, Debug)]
 struct NumCell {
-    value: i32,
+    pos: (usize, usize),
+    value: usize,
     adj: Vec<usize>,
-    on: i32,
-    unk: i32,
-}
-
-#[derive(Clone, Debug)]
-enum Action {
-    CellState { idx: usize, prev: CellState },
-    RowLight { seg: usize, prev: Option<usize> },
-    ColLight { seg: usize, prev: Option<usize> },
-    RowFree { seg: usize, prev: i32 },
-    ColFree { seg: usize, prev: i32 },
-    LitCount { idx: usize, prev: i32 },
-    NumOn { idx: usize, prev: i32 },
-    NumUnk { idx: usize, prev: i32 },
 }
 
-#[derive(Clone, Debug)]
-struct Core {
-    n_empty: usize,
+/// 盤面から決まり，探索中に変化しない情報
+#[derive(Debug)]
+struct Layout {
     empty_pos: Vec<(usize, usize)>,
+    empty_id: Vec<Vec<Option<usize>>>,
     row_seg_id: Vec<usize>,
     col_seg_id: Vec<usize>,
-    row_segs: Vec<Segment>,
-    col_segs: Vec<Segment>,
+    /// 行のセグメント，続いて列のセグメントのセル
+    ///
+    /// セグメントのセルは，そのセグメントのセルが Blocked になったときに調べ直すセルの監視リストを兼ねる．
+    /// 短く，セルごとに記録や監視を伴って走査するので，ビット集合ではなく番号の並びで持つ
+    /// （`docs/alg-fast.md` を参照）．
+    segs: Vec<Vec<usize>>,
     num_cells: Vec<NumCell>,
     num_adj_of_empty: Vec<Vec<usize>>,
-    lit_list: Vec<Vec<usize>>,
-    lit_count: Vec<i32>,
-    cell_state: Vec<CellState>,
-    trail: Vec<Action>,
+    /// 各セルを照らしうるセル（同じ行と列のセグメントの和）
+    lit_mask: Vec<BitSet>,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct Fast;
+#[derive(Clone)]
+pub(crate) struct Core {
+    layout: Arc<Layout>,
+    light: BitSet,
+    blocked: BitSet,
+    /// 照らされているセル
+    lit: BitSet,
+    /// 照らされている必要があるセル
+    need_lit: BitSet,
+    /// 各セグメントの Blocked でないセルの数
+    seg_free: Vec<u32>,
+    /// 割り当て済みのセルの数
+    assigned: usize,
+    /// チェックポイントごとの `light`，`blocked`，`lit`，`seg_free`，`assigned` の写し
+    snapshots: Vec<u64>,
+    /// 制約を調べ直す数字セル
+    num_queue: VecDeque<usize>,
+    /// 未照明セルを調べ直すセグメント
+    seg_queue: VecDeque<usize>,
+    seg_queued: Vec<bool>,
+    /// 分岐の深さ
+    depth: usize,
+    /// 割り当ての記録（記録しない場合は `None`）
+    trace: Option<Vec<TraceStep>>,
+    stats: SearchStats,
+    /// 伝播の後に仮置きによる先読みをするか
+    lookahead: bool,
+    /// 探索の打ち切り条件
+    cancel: Cancel,
+    /// 打ち切り条件に達して探索を途中でやめたか
+    interrupted: bool,
+    observer: Arc<dyn Observer>,
+}
+
+/// 並列探索で 1 スレッドあたりに用意する部分問題の数
+const TASKS_PER_THREAD: usize = 4;
+
+#[derive(Clone)]
+pub struct Fast {
+    /// 探索の進捗の通知先
+    observer: Arc<dyn Observer>,
+    /// 伝播の後に仮置きによる先読みをするか
+    lookahead: bool,
+}
+
+impl Default for Fast {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Fast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fast")
+            .field("lookahead", &self.lookahead)
+            .finish_non_exhaustive()
+    }
+}
+
+/// セルに割り当てた状態
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assignment {
+    /// あかりを置く
+    Light,
+    /// あかりを置かない
+    Blocked,
+}
+
+/// 割り当ての理由
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cause {
+    /// 数字セル `pos` の制約
+    Number { pos: (usize, usize) },
+    /// 同じ行または列のセグメントにあかり `light` がある
+    Segment { light: (usize, usize) },
+    /// 未照明セル `target` を照らせる候補が 1 つだけ
+    SingleCandidate { target: (usize, usize) },
+    /// 分岐による仮定
+    Branch,
+    /// 途中盤面で与えられた
+    Given,
+    /// 逆を仮定して伝播すると矛盾する（先読み）
+    Probe,
+}
+
+/// 求解の記録の 1 手
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    /// 割り当てたセル
+    pub pos: (usize, usize),
+    pub assignment: Assignment,
+    pub cause: Cause,
+    /// 割り当て時の分岐の深さ（分岐前は 0）
+    pub depth: usize,
+}
+
+/// 探索の統計
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// 展開した探索ノードの数
+    pub nodes: usize,
+    /// 分岐した探索ノードの数
+    pub branches: usize,
+    /// 分岐の最大の深さ
+    pub max_depth: usize,
+    /// 分岐で試した候補が矛盾して取り消した回数
+    pub backtracks: usize,
+    /// 伝播の反復の回数
+    pub propagation_rounds: usize,
+    /// 数字の制約で確定したセルの数
+    pub forced_by_number: usize,
+    /// 同じセグメントのあかりで確定したセルの数
+    pub forced_by_segment: usize,
+    /// 候補が 1 つだけで確定したセルの数
+    pub forced_by_single_candidate: usize,
+    /// 先読みで確定したセルの数
+    pub forced_by_probe: usize,
+    /// 先読みで仮置きを試した回数
+    pub probes: usize,
+    /// 割り当て済みのセルの数の最大値
+    pub max_trail: usize,
+    /// 求解にかかった時間
+    pub elapsed: Duration,
+}
+
+/// 途中盤面からの求解結果
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartialResult {
+    /// 途中盤面の印と矛盾しない解
+    Solved(Solution),
+    /// 解に至らない途中盤面の印のうち，どれを 1 つ外しても解が存在するようになる組
+    Contradiction(Vec<(usize, usize)>),
+    /// 途中盤面によらず盤面に解が存在しない
+    Unsolvable,
+    /// 解や矛盾が分かる前に打ち切られた
+    Interrupted,
+}
+
+/// 解の存在しない盤面のうち，それだけで解が存在しなくなる制約の組
+///
+/// `minimal` なら，どの制約を 1 つ外しても解が存在するようになる．
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatCore {
+    /// 数字セル（外すと数字のない壁とみなす）
+    pub clues: Vec<(usize, usize)>,
+    /// 照らされている必要がある空白セル
+    pub cells: Vec<(usize, usize)>,
+    /// 極小まで絞り込めたか（途中で打ち切られれば `false`）
+    pub minimal: bool,
+}
+
+/// 解の個数による盤面の分類
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uniqueness {
+    /// 解が存在しない
+    NoSolution,
+    /// 解がちょうど 1 つ存在する
+    Unique(Solution),
+    /// 解が 2 つ以上存在する
+    Multiple,
+    /// 判定できる前に打ち切られた
+    Interrupted,
+}
 
 impl Fast {
     pub fn new() -> Self {
-        Self
+        Self {
+            observer: Arc::new(NullObserver),
+            lookahead: false,
+        }
+    }
+
+    /// 伝播で確定しなくなったら，未確定のセルごとにあかりを置く場合と置かない場合を仮定して伝播し，
+    /// 矛盾する方を除く（既定では行わない）
+    pub fn with_lookahead(mut self, lookahead: bool) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    /// 探索の進捗を `observer` に通知する
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
+    fn core(&self, field: &Field, cancel: &Cancel) -> Core {
+        let mut core = Core::new(field);
+        core.observer = Arc::clone(&self.observer);
+        core.lookahead = self.lookahead;
+        core.cancel = cancel.clone();
+        core
+    }
+
+    /// 解とともに，セルへの割り当てをすべて順に記録したものを返す
+    ///
+    /// 記録にはバックトラックで取り消された割り当ても含まれる．
+    /// 深さが減少した箇所で，それより深い割り当てが取り消されている．
+    pub fn solve_with_trace(&self, field: &Field) -> (Option<Solution>, Vec<TraceStep>) {
+        let (solution, trace, _) = self.solve_traced(field, true);
+        (solution, trace)
+    }
+
+    /// 解とともに探索の統計を返す
+    pub fn solve_with_stats(&self, field: &Field) -> (Option<Solution>, SearchStats) {
+        let (solution, _, stats) = self.solve_traced(field, false);
+        (solution, stats)
+    }
+
+    /// 解とともに，割り当ての記録（`trace` が `true` のときのみ）と探索の統計を返す
+    pub(crate) fn solve_traced(
+        &self,
+        field: &Field,
+        trace: bool,
+    ) -> (Option<Solution>, Vec<TraceStep>, SearchStats) {
+        let start = Instant::now();
+        let mut core = self.core(field, &Cancel::new());
+        if trace {
+            core.trace = Some(Vec::new());
+        }
+        let solution = core.dfs().then(|| core.to_solution(field));
+        core.stats.max_trail = core.stats.max_trail.max(core.assigned);
+        core.stats.elapsed = start.elapsed();
+        self.observer.notify(Event::Finished);
+        (solution, core.trace.unwrap_or_default(), core.stats)
+    }
+
+    /// 途中盤面で置かれたあかりと印を固定して残りを解く
+    ///
+    /// 解が存在しなければ，矛盾の原因となっている印の組を削除法で絞り込んで返す．
+    /// 空白セル以外の印は無視する．
+    pub fn solve_partial(&self, field: &Field, partial: &PartialSolution) -> PartialResult {
+        self.solve_partial_with(field, partial, &Cancel::new())
+    }
+
+    /// `cancel` で打ち切られるまで途中盤面から解く
+    ///
+    /// 印の組を絞り込む途中で打ち切られた場合は，それまでに絞り込んだ組（極小とは限らない）を返す．
+    pub fn solve_partial_with(
+        &self,
+        field: &Field,
+        partial: &PartialSolution,
+        cancel: &Cancel,
+    ) -> PartialResult {
+        let result = self.narrow_marks(field, partial, cancel);
+        self.observer.notify(Event::Finished);
+        result
+    }
+
+    fn narrow_marks(
+        &self,
+        field: &Field,
+        partial: &PartialSolution,
+        cancel: &Cancel,
+    ) -> PartialResult {
+        let marks: Vec<_> = (0..field.h * field.w)
+            .map(|i| (i / field.w, i % field.w))
+            .filter(|&(r, c)| field.field[r][c].is_empty())
+            .map(|(r, c)| ((r, c), partial.field[r][c]))
+            .filter(|&(_, mark)| mark != Mark::Unknown)
+            .collect();
+
+        match self.solve_with_marks(field, &marks, cancel) {
+            SolveResult::Solved(sol) => return PartialResult::Solved(sol),
+            SolveResult::Interrupted => return PartialResult::Interrupted,
+            SolveResult::Unsolvable => {}
+        }
+        match self.solve_with_marks(field, &[], cancel) {
+            SolveResult::Unsolvable => return PartialResult::Unsolvable,
+            SolveResult::Interrupted => return PartialResult::Interrupted,
+            SolveResult::Solved(_) => {}
+        }
+
+        // 外しても解が存在しない印は原因ではないので取り除く
+        let mut conflict = marks;
+        let mut i = 0;
+        while i < conflict.len() {
+            let mut rest = conflict.clone();
+            rest.remove(i);
+            match self.solve_with_marks(field, &rest, cancel) {
+                SolveResult::Unsolvable => conflict = rest,
+                SolveResult::Solved(_) => i += 1,
+                SolveResult::Interrupted => break,
+            }
+        }
+
+        PartialResult::Contradiction(conflict.into_iter().map(|(pos, _)| pos).collect())
+    }
+
+    /// 解が存在しなければ，矛盾の原因となる数字セルと空白セルの組を返す
+    ///
+    /// 数字セルを，続いて照らす必要のある空白セルを 1 つずつ外し，
+    /// 外しても解が存在しないものを取り除く（削除法）．
+    /// 制約を外すと解は増える一方なので，結果は極小になる．
+    pub fn unsat_core(&self, field: &Field) -> Option<UnsatCore> {
+        self.unsat_core_with(field, &Cancel::new())
+    }
+
+    /// `cancel` で打ち切られるまで矛盾の原因を絞り込む
+    ///
+    /// 解が存在しないと分かる前に打ち切られれば `None` を，
+    /// 絞り込む途中で打ち切られれば，まだ調べていない制約をすべて残した組（極小とは限らない）を返す．
+    pub fn unsat_core_with(&self, field: &Field, cancel: &Cancel) -> Option<UnsatCore> {
+        let core = self.narrow_core(field, cancel);
+        self.observer.notify(Event::Finished);
+        core
+    }
+
+    fn narrow_core(&self, field: &Field, cancel: &Cancel) -> Option<UnsatCore> {
+        let all_cells: Vec<_> = (0..field.h * field.w)
+            .map(|i| (i / field.w, i % field.w))
+            .filter(|&(r, c)| field.field[r][c].is_empty())
+            .collect();
+        if self.solve_with_need_lit(field, &all_cells, cancel) != SolveResult::Unsolvable {
+            return None;
+        }
+
+        // 外すと解が存在するようになる（または打ち切られて分からない）制約を残す
+        let mut interrupted = false;
+        let mut needed = |field: &Field, need_lit: &[(usize, usize)]| {
+            if !interrupted {
+                match self.solve_with_need_lit(field, need_lit, cancel) {
+                    SolveResult::Unsolvable => return false,
+                    SolveResult::Solved(_) => {}
+                    SolveResult::Interrupted => interrupted = true,
+                }
+            }
+            true
+        };
+
+        let mut field = field.clone();
+        let mut clues = vec![];
+        for r in 0..field.h {
+            for c in 0..field.w {
+                let prev = field.field[r][c];
+                if prev.is_adj().is_none() {
+                    continue;
+                }
+                field.field[r][c] = State::Nil;
+                if needed(&field, &all_cells) {
+                    field.field[r][c] = prev;
+                    clues.push((r, c));
+                }
+            }
+        }
+
+        let mut cells = all_cells;
+        let mut i = 0;
+        while i < cells.len() {
+            let mut rest = cells.clone();
+            rest.remove(i);
+            if needed(&field, &rest) {
+                i += 1;
+            } else {
+                cells = rest;
+            }
+        }
+
+        Some(UnsatCore {
+            clues,
+            cells,
+            minimal: !interrupted,
+        })
     }
+
+    /// `need_lit` のセルだけが照らされている必要があるとして解く
+    fn solve_with_need_lit(
+        &self,
+        field: &Field,
+        need_lit: &[(usize, usize)],
+        cancel: &Cancel,
+    ) -> SolveResult {
+        let mut core = self.core(field, cancel);
+        core.need_lit = BitSet::new(core.layout.empty_pos.len());
+        for &(r, c) in need_lit {
+            if let Some(cell) = core.layout.empty_id[r][c] {
+                core.need_lit.insert(cell);
+            }
+        }
+        let found = core.dfs().then(|| core.to_solution(field));
+        SolveResult::finish(found, core.interrupted)
+    }
+
+    /// 途中盤面の印を固定して解く
+    fn solve_with_marks(
+        &self,
+        field: &Field,
+        marks: &[((usize, usize), Mark)],
+        cancel: &Cancel,
+    ) -> SolveResult {
+        let mut core = self.core(field, cancel);
+        for &((r, c), mark) in marks {
+            let Some(cell) = core.layout.empty_id[r][c] else {
+                return SolveResult::Unsolvable;
+            };
+            let ok = match mark {
+                Mark::Light => core.set_light(cell, Cause::Given),
+                Mark::Dot => core.set_blocked(cell, Cause::Given),
+                Mark::Unknown => true,
+            };
+            if !ok {
+                return SolveResult::Unsolvable;
+            }
+        }
+        let found = core.dfs().then(|| core.to_solution(field));
+        SolveResult::finish(found, core.interrupted)
+    }
+
+    /// 解を最大 `limit` 個まで数える
+    pub fn count_solutions(&self, field: &Field, limit: usize) -> usize {
+        self.solutions(field).take(limit).count()
+    }
+
+    /// `cancel` で打ち切られるまで解を最大 `limit` 個まで数える
+    ///
+    /// `limit` 個に達するか列挙し終える前に打ち切られれば `None` を返す．
+    pub fn count_solutions_with(
+        &self,
+        field: &Field,
+        limit: usize,
+        cancel: &Cancel,
+    ) -> Option<usize> {
+        let mut solutions = self.solutions_with(field, cancel);
+        let count = solutions.by_ref().take(limit).count();
+        (count == limit || !solutions.is_interrupted()).then_some(count)
+    }
+
+    /// 解が存在しないか，一意か，複数存在するかを判定する
+    pub fn uniqueness(&self, field: &Field) -> Uniqueness {
+        self.uniqueness_with(field, &Cancel::new())
+    }
+
+    /// `cancel` で打ち切られるまでに解の個数を判定する
+    pub fn uniqueness_with(&self, field: &Field, cancel: &Cancel) -> Uniqueness {
+        let mut solutions = self.solutions_with(field, cancel);
+        match (solutions.next(), solutions.next()) {
+            (Some(_), Some(_)) => Uniqueness::Multiple,
+            _ if solutions.is_interrupted() => Uniqueness::Interrupted,
+            (None, _) => Uniqueness::NoSolution,
+            (Some(sol), None) => Uniqueness::Unique(sol),
+        }
+    }
+
+    /// すべての解を 1 つずつ列挙するイテレータを返す
+    pub fn solutions<'a>(&self, field: &'a Field) -> Solutions<'a> {
+        self.solutions_with(field, &Cancel::new())
+    }
+
+    /// `cancel` で打ち切られるまで解を列挙するイテレータを返す
+    ///
+    /// 打ち切られると列挙を終える．[`Solutions::is_interrupted`] で途中で終えたかを判定できる．
+    pub fn solutions_with<'a>(&self, field: &'a Field, cancel: &Cancel) -> Solutions<'a> {
+        Solutions::new(field, self.core(field, cancel), true)
+    }
+
+    /// `threads` 個のスレッドで分岐候補を分担して解を 1 つ求める
+    ///
+    /// いずれかのスレッドが解を見つけるか `cancel` で打ち切られた時点で全スレッドが探索を打ち切る．
+    /// スレッド数には [`std::thread::available_parallelism`] などを渡す．
+    pub fn solve_parallel(&self, field: &Field, threads: usize, cancel: &Cancel) -> SolveResult {
+        let threads = threads.max(1);
+        let (tasks, solved) = split(self.core(field, cancel), threads * TASKS_PER_THREAD);
+        if let Some(core) = solved.first() {
+            self.observer.notify(Event::Finished);
+            return SolveResult::Solved(core.to_solution(field));
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let next = AtomicUsize::new(0);
+        let found = Mutex::new(None);
+        let interrupted = AtomicBool::new(false);
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    while !stop.load(Ordering::Relaxed) {
+                        let Some(task) = tasks.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        let mut core = task.clone();
+                        core.cancel = cancel.clone().with_flag(Arc::clone(&stop));
+                        if core.dfs() {
+                            stop.store(true, Ordering::Relaxed);
+                            found
+                                .lock()
+                                .unwrap()
+                                .get_or_insert_with(|| core.to_solution(field));
+                        }
+                        if core.interrupted {
+                            interrupted.store(true, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        self.observer.notify(Event::Finished);
+        SolveResult::finish(found.into_inner().unwrap(), interrupted.into_inner())
+    }
+
+    /// `threads` 個のスレッドで解を最大 `limit` 個まで数える
+    ///
+    /// 各スレッドの数えた個数を合計し，`limit` に達するか `cancel` で打ち切られたら全スレッドが打ち切る．
+    /// [`Fast::count_solutions_with`] と同じく，数え終える前に打ち切られれば `None` を返す．
+    pub fn count_solutions_parallel(
+        &self,
+        field: &Field,
+        limit: usize,
+        threads: usize,
+        cancel: &Cancel,
+    ) -> Option<usize> {
+        let threads = threads.max(1);
+        let (tasks, solved) = split(self.core(field, cancel), threads * TASKS_PER_THREAD);
+        if solved.len() >= limit {
+            self.observer.notify(Event::Finished);
+            return Some(limit);
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let next = AtomicUsize::new(0);
+        let total = AtomicUsize::new(solved.len());
+        let interrupted = AtomicBool::new(false);
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    while !stop.load(Ordering::Relaxed) {
+                        let Some(task) = tasks.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        let mut core = task.clone();
+                        core.cancel = cancel.clone().with_flag(Arc::clone(&stop));
+                        let mut solutions = Solutions::new(field, core, false);
+                        for _ in solutions.by_ref() {
+                            if total.fetch_add(1, Ordering::Relaxed) + 1 >= limit {
+                                stop.store(true, Ordering::Relaxed);
+                                break;
+                            }
+                        }
+                        if solutions.is_interrupted() {
+                            interrupted.store(true, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        self.observer.notify(Event::Finished);
+        let total = total.into_inner().min(limit);
+        (total == limit || !interrupted.into_inner()).then_some(total)
+    }
+}
+
+/// 探索木を幅優先に展開し，`target` 個以上の部分問題に分ける
+///
+/// 枝は [`Solutions`] と同じく排反にするので，部分問題の解を合わせると元の問題の解になる．
+/// 展開中に解けた部分問題は 2 つ目の戻り値に入る．
+fn split(core: Core, target: usize) -> (Vec<Core>, Vec<Core>) {
+    let mut queue = VecDeque::from([core]);
+    let mut solved = Vec::new();
+    while queue.len() < target {
+        let Some(mut core) = queue.pop_front() else {
+            break;
+        };
+        // 打ち切られていれば残りは各スレッドの探索に任せる（すぐに打ち切られる）
+        if core.stopped() {
+            queue.push_front(core);
+            break;
+        }
+        if !core.settle() {
+            continue;
+        }
+        if core.is_solved() {
+            solved.push(core);
+            continue;
+        }
+        let Some(candidates) = core.choose_branch_cell() else {
+            continue;
+        };
+        for (i, &pos) in candidates.iter().enumerate() {
+            let mut child = core.clone();
+            let ok = candidates[..i]
+                .iter()
+                .all(|&prev| child.set_blocked(prev, Cause::Branch))
+                && child.set_light(pos, Cause::Branch);
+            if ok {
+                queue.push_back(child);
+            }
+        }
+    }
+    (queue.into(), solved)
 }
 
 impl Solver for Fast {
-    fn solve(&self, field: &Field) -> Option<Solution> {
-        let mut core = Core::new(field);
-        if core.dfs() {
-            Some(core.to_solution(field))
-        } else {
-            None
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
+        let mut core = self.core(field, cancel);
+        let found = core.dfs().then(|| core.to_solution(field));
+        self.observer.notify(Event::Finished);
+        SolveResult::finish(found, core.interrupted)
+    }
+}
+
+/// 探索中の分岐点
+#[derive(Clone, Debug)]
+struct Frame {
+    /// 分岐前のチェックポイント
+    cp: usize,
+    /// 未照明セルを照らしうる候補
+    candidates: Vec<usize>,
+    /// 次に試す候補の添字
+    next: usize,
+}
+
+/// 解を遅延評価で列挙するイテレータ
+///
+/// 分岐では先に試した候補を Blocked にして枝を排反にするため，同じ解を重複して返さない．
+/// 探索状態はチェックポイント（`checkpoint` / `undo`）で巻き戻すので，保持するのは分岐点のスタックのみ．
+///
+/// 列挙を終えるか破棄されたときに [`Event::Finished`] を通知する．
+pub struct Solutions<'a> {
+    field: &'a Field,
+    core: Core,
+    stack: Vec<Frame>,
+    started: bool,
+    /// まだ [`Event::Finished`] を通知していないか（並列探索の部分問題では通知しない）
+    report: bool,
+}
+
+impl<'a> Solutions<'a> {
+    fn new(field: &'a Field, core: Core, report: bool) -> Self {
+        Solutions {
+            field,
+            core,
+            stack: Vec::new(),
+            started: false,
+            report,
+        }
+    }
+
+    /// 一度だけ [`Event::Finished`] を通知する
+    fn finish(&mut self) {
+        if std::mem::take(&mut self.report) {
+            self.core.observer.notify(Event::Finished);
+        }
+    }
+
+    /// 打ち切られて列挙を途中で終えたか
+    pub fn is_interrupted(&self) -> bool {
+        self.core.interrupted
+    }
+
+    /// 現在の状態を伝播し，解ならそれを返し，未確定なら分岐点を積む
+    fn expand(&mut self) -> Option<Solution> {
+        self.core.expanded(self.stack.len());
+        if !self.core.settle() {
+            return None;
+        }
+        if self.core.is_solved() {
+            self.core.observer.notify(Event::SolutionFound);
+            return Some(self.core.to_solution(self.field));
+        }
+        if let Some(candidates) = self.core.choose_branch_cell() {
+            self.stack.push(Frame {
+                cp: self.core.checkpoint(),
+                candidates,
+                next: 0,
+            });
+        }
+        None
+    }
+}
+
+impl Drop for Solutions<'_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Solution;
+
+    fn next(&mut self) -> Option<Solution> {
+        let sol = self.search_next();
+        if sol.is_none() {
+            self.finish();
+        }
+        sol
+    }
+}
+
+impl Solutions<'_> {
+    fn search_next(&mut self) -> Option<Solution> {
+        if !self.started {
+            self.started = true;
+            if let Some(sol) = self.expand() {
+                return Some(sol);
+            }
+        }
+
+        while let Some(frame) = self.stack.last_mut() {
+            if self.core.stopped() {
+                return None;
+            }
+            let cp = frame.cp;
+            self.core.undo(cp);
+            if frame.next == frame.candidates.len() {
+                self.stack.pop();
+                self.core.release(cp);
+                continue;
+            }
+
+            let i = frame.next;
+            let pos = frame.candidates[i];
+            frame.next += 1;
+
+            // 先に試した候補にはあかりを置かない
+            let ok = frame.candidates[..i]
+                .iter()
+                .all(|&prev| self.core.set_blocked(prev, Cause::Branch))
+                && self.core.set_light(pos, Cause::Branch);
+            if ok && let Some(sol) = self.expand() {
+                return Some(sol);
+            }
         }
+
+        None
     }
 }
 
 impl Core {
-    fn new(field: &Field) -> Self {
+    pub(crate) fn new(field: &Field) -> Self {
         let h = field.h;
         let w = field.w;
 
@@ -107,11 +803,7 @@ impl Core {
                         cells.push(id);
                         c += 1;
                     }
-                    row_segs.push(Segment {
-                        light: None,
-                        free_cnt: cells.len() as i32,
-                        cells,
-                    });
+                    row_segs.push(cells);
                 } else {
                     c += 1;
                 }
@@ -130,11 +822,7 @@ impl Core {
                         cells.push(id);
                         r += 1;
                     }
-                    col_segs.push(Segment {
-                        light: None,
-                        free_cnt: cells.len() as i32,
-                        cells,
-                    });
+                    col_segs.push(cells);
                 } else {
                     r += 1;
                 }
@@ -154,177 +842,243 @@ impl Core {
                             num_adj_of_empty[id].push(num_cells.len());
                         }
                     }
-                    let unk = adj.len() as i32;
                     num_cells.push(NumCell {
-                        value: value as i32,
+                        pos: (r, c),
+                        value,
                         adj,
-                        on: 0,
-                        unk,
                     });
                 }
             }
         }
 
-        let mut lit_list = Vec::with_capacity(n_empty);
-        for id in 0..n_empty {
-            let mut list = row_segs[row_seg_id[id]].cells.clone();
-            for &x in &col_segs[col_seg_id[id]].cells {
-                if !list.contains(&x) {
-                    list.push(x);
+        let lit_mask = (0..n_empty)
+            .map(|id| {
+                let mut mask = BitSet::new(n_empty);
+                for &x in row_segs[row_seg_id[id]]
+                    .iter()
+                    .chain(&col_segs[col_seg_id[id]])
+                {
+                    mask.insert(x);
                 }
-            }
-            lit_list.push(list);
+                mask
+            })
+            .collect();
+
+        for id in col_seg_id.iter_mut() {
+            *id += row_segs.len();
         }
+        let mut segs = row_segs;
+        segs.append(&mut col_segs);
+        let seg_free = segs.iter().map(|cells| cells.len() as u32).collect();
+        let n_segs = segs.len();
+        let n_nums = num_cells.len();
 
         Core {
-            n_empty,
-            empty_pos,
-            row_seg_id,
-            col_seg_id,
-            row_segs,
-            col_segs,
-            num_cells,
-            num_adj_of_empty,
-            lit_list,
-            lit_count: vec![0; n_empty],
-            cell_state: vec![CellState::Unknown; n_empty],
-            trail: Vec::new(),
+            layout: Arc::new(Layout {
+                empty_pos,
+                empty_id,
+                row_seg_id,
+                col_seg_id,
+                segs,
+                num_cells,
+                num_adj_of_empty,
+                lit_mask,
+            }),
+            light: BitSet::new(n_empty),
+            blocked: BitSet::new(n_empty),
+            lit: BitSet::new(n_empty),
+            need_lit: BitSet::full(n_empty),
+            seg_free,
+            assigned: 0,
+            snapshots: Vec::new(),
+            num_queue: (0..n_nums).collect(),
+            seg_queue: (0..n_segs).collect(),
+            seg_queued: vec![true; n_segs],
+            depth: 0,
+            trace: None,
+            stats: SearchStats::default(),
+            lookahead: false,
+            cancel: Cancel::new(),
+            interrupted: false,
+            observer: Arc::new(NullObserver),
         }
     }
 
+    /// 空白セルの位置（添字が空白セルの番号）
+    pub(crate) fn empty_pos(&self) -> &[(usize, usize)] {
+        &self.layout.empty_pos
+    }
+
+    /// 行と列のセグメントに含まれる空白セルの番号
+    pub(crate) fn segments(&self) -> impl Iterator<Item = &[usize]> {
+        self.layout.segs.iter().map(Vec::as_slice)
+    }
+
+    /// 数字セルの値と隣接する空白セルの番号
+    pub(crate) fn numbers(&self) -> impl Iterator<Item = (usize, &[usize])> {
+        self.layout
+            .num_cells
+            .iter()
+            .map(|n| (n.value, n.adj.as_slice()))
+    }
+
+    /// 空白セル `cell` を照らしうるセルの番号（`cell` 自身を含む）
+    pub(crate) fn lit_cells(&self, cell: usize) -> impl Iterator<Item = usize> {
+        self.layout.lit_mask[cell].iter()
+    }
+
     fn to_solution(&self, field: &Field) -> Solution {
         let mut grid = vec![vec![false; field.w]; field.h];
-        for (id, &(r, c)) in self.empty_pos.iter().enumerate() {
-            if self.cell_state[id] == CellState::Light {
-                grid[r][c] = true;
-            }
+        for cell in self.light.iter() {
+            let (r, c) = self.layout.empty_pos[cell];
+            grid[r][c] = true;
         }
         Solution { field: grid }
     }
 
-    fn checkpoint(&self) -> usize {
-        self.trail.len()
+    /// ノードの展開を通知する
+    fn expanded(&mut self, depth: usize) {
+        self.stats.nodes += 1;
+        self.observer.notify(Event::NodeExpanded {
+            nodes: self.stats.nodes,
+            depth,
+        });
     }
 
-    fn undo(&mut self, cp: usize) {
-        while self.trail.len() > cp {
-            match self.trail.pop().unwrap() {
-                Action::CellState { idx, prev } => self.cell_state[idx] = prev,
-                Action::RowLight { seg, prev } => self.row_segs[seg].light = prev,
-                Action::ColLight { seg, prev } => self.col_segs[seg].light = prev,
-                Action::RowFree { seg, prev } => self.row_segs[seg].free_cnt = prev,
-                Action::ColFree { seg, prev } => self.col_segs[seg].free_cnt = prev,
-                Action::LitCount { idx, prev } => self.lit_count[idx] = prev,
-                Action::NumOn { idx, prev } => self.num_cells[idx].on = prev,
-                Action::NumUnk { idx, prev } => self.num_cells[idx].unk = prev,
-            }
+    /// 作業量を 1 消費し，打ち切るべきかを返す（ノードごとに呼ぶ）
+    fn stopped(&mut self) -> bool {
+        let stop = self.cancel.is_cancelled();
+        self.interrupted |= stop;
+        stop
+    }
+
+    /// 作業量を消費せずに，打ち切るべきかを返す
+    fn expired(&mut self) -> bool {
+        let stop = self.cancel.is_expired();
+        self.interrupted |= stop;
+        stop
+    }
+
+    fn is_unknown(&self, cell: usize) -> bool {
+        !self.light.contains(cell) && !self.blocked.contains(cell)
+    }
+
+    /// 現在の状態を保存し，[`Core::undo`] に渡す位置を返す
+    fn checkpoint(&mut self) -> usize {
+        let cp = self.snapshots.len();
+        for set in [&self.light, &self.blocked, &self.lit] {
+            self.snapshots.extend_from_slice(set.words());
         }
+        self.snapshots
+            .extend(self.seg_free.iter().map(|&free| free as u64));
+        self.snapshots.push(self.assigned as u64);
+        cp
     }
 
-    fn set_blocked(&mut self, cell: usize, q_num: &mut VecDeque<usize>) -> bool {
-        match self.cell_state[cell] {
-            CellState::Blocked => return true,
-            CellState::Light => return false,
-            CellState::Unknown => {}
+    /// チェックポイント `cp` の状態に戻す（`cp` は再び使える）
+    fn undo(&mut self, cp: usize) {
+        self.stats.max_trail = self.stats.max_trail.max(self.assigned);
+        let n = self.light.words().len();
+        let saved = &self.snapshots[cp..];
+        self.light.words_mut().copy_from_slice(&saved[..n]);
+        self.blocked.words_mut().copy_from_slice(&saved[n..2 * n]);
+        self.lit.words_mut().copy_from_slice(&saved[2 * n..3 * n]);
+        let m = self.seg_free.len();
+        for (free, &saved) in self.seg_free.iter_mut().zip(&saved[3 * n..3 * n + m]) {
+            *free = saved as u32;
         }
-        self.trail.push(Action::CellState {
-            idx: cell,
-            prev: CellState::Unknown,
-        });
-        self.cell_state[cell] = CellState::Blocked;
+        self.assigned = saved[3 * n + m] as usize;
+        self.snapshots.truncate(cp + 3 * n + m + 1);
 
-        let rseg = self.row_seg_id[cell];
-        let prev_row = self.row_segs[rseg].free_cnt;
-        self.trail.push(Action::RowFree {
-            seg: rseg,
-            prev: prev_row,
-        });
-        self.row_segs[rseg].free_cnt -= 1;
+        // チェックポイントは伝播が済んだ状態で取るので，調べ直すものはない
+        self.num_queue.clear();
+        for seg in self.seg_queue.drain(..) {
+            self.seg_queued[seg] = false;
+        }
+    }
 
-        let cseg = self.col_seg_id[cell];
-        let prev_col = self.col_segs[cseg].free_cnt;
-        self.trail.push(Action::ColFree {
-            seg: cseg,
-            prev: prev_col,
-        });
-        self.col_segs[cseg].free_cnt -= 1;
+    /// チェックポイント `cp` 以降を捨てる
+    fn release(&mut self, cp: usize) {
+        self.snapshots.truncate(cp);
+    }
 
-        for &idx in &self.num_adj_of_empty[cell] {
-            let prev_unk = self.num_cells[idx].unk;
-            self.trail.push(Action::NumUnk {
-                idx,
-                prev: prev_unk,
+    fn record(&mut self, cell: usize, assignment: Assignment, cause: Cause) {
+        self.assigned += 1;
+        match cause {
+            Cause::Number { .. } => self.stats.forced_by_number += 1,
+            Cause::Segment { .. } => self.stats.forced_by_segment += 1,
+            Cause::SingleCandidate { .. } => self.stats.forced_by_single_candidate += 1,
+            Cause::Probe => self.stats.forced_by_probe += 1,
+            Cause::Branch | Cause::Given => {}
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceStep {
+                pos: self.layout.empty_pos[cell],
+                assignment,
+                cause,
+                depth: self.depth,
             });
-            self.num_cells[idx].unk -= 1;
-            q_num.push_back(idx);
         }
+    }
 
-        true
+    /// 数字セル `idx` の周りのあかりの数と未確定のセルの数
+    fn number_counts(&self, idx: usize) -> (usize, usize) {
+        let adj = &self.layout.num_cells[idx].adj;
+        let on = adj.iter().filter(|&&x| self.light.contains(x)).count();
+        let unk = adj.iter().filter(|&&x| self.is_unknown(x)).count();
+        (on, unk)
     }
 
-    fn set_light(&mut self, cell: usize, q_num: &mut VecDeque<usize>) -> bool {
-        match self.cell_state[cell] {
-            CellState::Light => return true,
-            CellState::Blocked => return false,
-            CellState::Unknown => {}
+    /// セグメント `seg` の未照明セルを調べ直す
+    fn watch(&mut self, seg: usize) {
+        if !self.seg_queued[seg] {
+            self.seg_queued[seg] = true;
+            self.seg_queue.push_back(seg);
         }
-        self.trail.push(Action::CellState {
-            idx: cell,
-            prev: CellState::Unknown,
-        });
-        self.cell_state[cell] = CellState::Light;
+    }
 
-        for &lit in &self.lit_list[cell] {
-            let prev = self.lit_count[lit];
-            self.trail.push(Action::LitCount { idx: lit, prev });
-            self.lit_count[lit] = prev + 1;
+    fn set_blocked(&mut self, cell: usize, cause: Cause) -> bool {
+        if self.blocked.contains(cell) {
+            return true;
         }
+        if self.light.contains(cell) {
+            return false;
+        }
+        self.blocked.insert(cell);
+        self.record(cell, Assignment::Blocked, cause);
+        self.num_queue.extend(&self.layout.num_adj_of_empty[cell]);
+        for seg in [self.layout.row_seg_id[cell], self.layout.col_seg_id[cell]] {
+            self.seg_free[seg] -= 1;
+            self.watch(seg);
+        }
+        true
+    }
 
-        for &idx in &self.num_adj_of_empty[cell] {
-            let prev_on = self.num_cells[idx].on;
-            self.trail.push(Action::NumOn {
-                idx,
-                prev: prev_on,
-            });
-            self.num_cells[idx].on = prev_on + 1;
-
-            let prev_unk = self.num_cells[idx].unk;
-            self.trail.push(Action::NumUnk {
-                idx,
-                prev: prev_unk,
-            });
-            self.num_cells[idx].unk = prev_unk - 1;
-            q_num.push_back(idx);
-        }
-
-        let rseg = self.row_seg_id[cell];
-        match self.row_segs[rseg].light {
-            Some(prev) if prev != cell => return false,
-            Some(_) => {}
-            None => {
-                self.trail.push(Action::RowLight { seg: rseg, prev: None });
-                self.row_segs[rseg].light = Some(cell);
-                let cells = self.row_segs[rseg].cells.clone();
-                for other in cells {
-                    if other != cell && !self.set_blocked(other, q_num) {
-                        return false;
-                    }
-                }
-            }
+    fn set_light(&mut self, cell: usize, cause: Cause) -> bool {
+        if self.light.contains(cell) {
+            return true;
+        }
+        if self.blocked.contains(cell) {
+            return false;
         }
+        self.light.insert(cell);
+        self.record(cell, Assignment::Light, cause);
 
-        let cseg = self.col_seg_id[cell];
-        match self.col_segs[cseg].light {
-            Some(prev) if prev != cell => return false,
-            Some(_) => {}
-            None => {
-                self.trail.push(Action::ColLight { seg: cseg, prev: None });
-                self.col_segs[cseg].light = Some(cell);
-                let cells = self.col_segs[cseg].cells.clone();
-                for other in cells {
-                    if other != cell && !self.set_blocked(other, q_num) {
-                        return false;
-                    }
+        let layout = Arc::clone(&self.layout);
+        self.lit.union_with(&layout.lit_mask[cell]);
+        self.num_queue.extend(&layout.num_adj_of_empty[cell]);
+
+        let segment = Cause::Segment {
+            light: layout.empty_pos[cell],
+        };
+        for seg in [layout.row_seg_id[cell], layout.col_seg_id[cell]] {
+            let cells = &layout.segs[seg];
+            if cells.iter().any(|&x| x != cell && self.light.contains(x)) {
+                return false;
+            }
+            for &other in cells {
+                if other != cell && !self.set_blocked(other, segment) {
+                    return false;
                 }
             }
         }
@@ -332,81 +1086,73 @@ impl Core {
         true
     }
 
+    /// 待ち行列の数字セルとセグメントだけを調べて伝播する
+    ///
+    /// セルが Blocked になると，そのセルを候補に含む未照明セル（同じ行と列のセグメントのセル）だけを調べ直す．
     fn propagate(&mut self) -> bool {
-        let mut q_num: VecDeque<usize> = (0..self.num_cells.len()).collect();
+        let layout = Arc::clone(&self.layout);
 
         loop {
-            let mut changed = false;
+            self.stats.propagation_rounds += 1;
 
-            while let Some(idx) = q_num.pop_front() {
-                let (on, unk, value) = {
-                    let n = &self.num_cells[idx];
-                    (n.on, n.unk, n.value)
-                };
+            while let Some(idx) = self.num_queue.pop_front() {
+                let n = &layout.num_cells[idx];
+                let (on, unk) = self.number_counts(idx);
+                let cause = Cause::Number { pos: n.pos };
 
-                if on > value || on + unk < value {
+                if on > n.value || on + unk < n.value {
                     return false;
                 }
+                if unk == 0 || (on != n.value && on + unk != n.value) {
+                    continue;
+                }
 
-                if on == value {
-                    let adj = self.num_cells[idx].adj.clone();
-                    for cell in adj {
-                        if self.cell_state[cell] == CellState::Unknown {
-                            if !self.set_blocked(cell, &mut q_num) {
-                                return false;
-                            }
-                            changed = true;
-                        }
+                for &cell in &n.adj {
+                    if !self.is_unknown(cell) {
+                        continue;
                     }
-                } else if on + unk == value {
-                    let adj = self.num_cells[idx].adj.clone();
-                    for cell in adj {
-                        if self.cell_state[cell] == CellState::Unknown {
-                            if !self.set_light(cell, &mut q_num) {
-                                return false;
-                            }
-                            changed = true;
-                        }
+                    let ok = if on == n.value {
+                        self.set_blocked(cell, cause)
+                    } else {
+                        self.set_light(cell, cause)
+                    };
+                    if !ok {
+                        return false;
                     }
                 }
             }
 
-            for cell in 0..self.n_empty {
-                if self.lit_count[cell] > 0 {
-                    continue;
-                }
-                let rseg = self.row_seg_id[cell];
-                let cseg = self.col_seg_id[cell];
-
-                if self.row_segs[rseg].light.is_some() || self.col_segs[cseg].light.is_some() {
-                    continue;
-                }
-
-                let row_free = self.row_segs[rseg].free_cnt;
-                let col_free = self.col_segs[cseg].free_cnt;
-                let self_free = if self.cell_state[cell] != CellState::Blocked {
-                    1
-                } else {
-                    0
-                };
-                let cand = row_free + col_free - self_free;
-
-                if cand == 0 {
-                    return false;
-                }
-                if cand == 1 {
-                    if let Some(pos) = self.find_single_candidate(rseg, cseg) {
-                        if !self.set_light(pos, &mut q_num) {
-                            return false;
+            while let Some(seg) = self.seg_queue.pop_front() {
+                self.seg_queued[seg] = false;
+                for &cell in &layout.segs[seg] {
+                    if self.lit.contains(cell) || !self.need_lit.contains(cell) {
+                        continue;
+                    }
+                    let row = layout.row_seg_id[cell];
+                    let col = layout.col_seg_id[cell];
+                    let own = !self.blocked.contains(cell) as u32;
+                    match self.seg_free[row] + self.seg_free[col] - own {
+                        0 => return false,
+                        1 => {
+                            let pos = layout.segs[row]
+                                .iter()
+                                .chain(&layout.segs[col])
+                                .copied()
+                                .find(|&x| !self.blocked.contains(x))
+                                .unwrap();
+                            let cause = Cause::SingleCandidate {
+                                target: layout.empty_pos[cell],
+                            };
+                            if !self.set_light(pos, cause) {
+                                return false;
+                            }
                         }
-                        changed = true;
-                    } else {
-                        return false;
+                        _ => {}
                     }
                 }
             }
 
-            if !changed && q_num.is_empty() {
+            if self.num_queue.is_empty() {
                 break;
             }
         }
@@ -414,92 +1160,124 @@ impl Core {
         true
     }
 
-    fn find_single_candidate(&self, rseg: usize, cseg: usize) -> Option<usize> {
-        let mut only = None;
-        for &cell in &self.row_segs[rseg].cells {
-            if self.cell_state[cell] != CellState::Blocked {
-                if let Some(prev) = only {
-                    if prev != cell {
-                        return None;
-                    }
-                } else {
-                    only = Some(cell);
+    /// 伝播し，先読みが有効ならさらに仮置きで確定させる
+    fn settle(&mut self) -> bool {
+        self.propagate() && (!self.lookahead || self.probe())
+    }
+
+    /// 未確定のセルごとに，あかりを置いて矛盾すれば置かず，置かずに矛盾すれば置く
+    ///
+    /// 確定するセルがなくなるまで繰り返す．両方とも矛盾すれば `false` を返す．
+    fn probe(&mut self) -> bool {
+        loop {
+            let mut changed = false;
+            for cell in 0..self.layout.empty_pos.len() {
+                // 作業量はノードごとに消費するので，仮置きでは消費せずに調べる
+                if self.expired() {
+                    return true;
                 }
-            }
-        }
-        for &cell in &self.col_segs[cseg].cells {
-            if self.cell_state[cell] != CellState::Blocked {
-                if let Some(prev) = only {
-                    if prev != cell {
-                        return None;
-                    }
+                if !self.is_unknown(cell) {
+                    continue;
+                }
+                let ok = if self.fails(cell, Assignment::Light) {
+                    self.set_blocked(cell, Cause::Probe)
+                } else if self.fails(cell, Assignment::Blocked) {
+                    self.set_light(cell, Cause::Probe)
                 } else {
-                    only = Some(cell);
+                    continue;
+                };
+                if !ok || !self.propagate() {
+                    return false;
                 }
+                changed = true;
+            }
+            if !changed {
+                return true;
             }
         }
-        only
+    }
+
+    /// `cell` に `assignment` を仮に割り当てて伝播すると矛盾するか
+    ///
+    /// 仮置きの間の記録と統計は残さない．
+    fn fails(&mut self, cell: usize, assignment: Assignment) -> bool {
+        let trace = self.trace.take();
+        let stats = self.stats;
+        let cp = self.checkpoint();
+        let ok = match assignment {
+            Assignment::Light => self.set_light(cell, Cause::Branch),
+            Assignment::Blocked => self.set_blocked(cell, Cause::Branch),
+        } && self.propagate();
+        self.undo(cp);
+        self.release(cp);
+        self.trace = trace;
+        self.stats = stats;
+        self.stats.probes += 1;
+        !ok
     }
 
     fn is_solved(&self) -> bool {
-        if self.lit_count.iter().any(|&v| v == 0) {
-            return false;
-        }
-        self.num_cells.iter().all(|n| n.on == n.value)
+        self.need_lit.is_subset(&self.lit)
+            && (0..self.layout.num_cells.len())
+                .all(|idx| self.number_counts(idx).0 == self.layout.num_cells[idx].value)
     }
 
     fn choose_branch_cell(&self) -> Option<Vec<usize>> {
-        let mut best: Option<(i32, Vec<usize>)> = None;
+        let layout = &self.layout;
+        let mut best: Option<(u32, usize)> = None;
 
-        for cell in 0..self.n_empty {
-            if self.lit_count[cell] > 0 {
-                continue;
-            }
-            let rseg = self.row_seg_id[cell];
-            let cseg = self.col_seg_id[cell];
-            if self.row_segs[rseg].light.is_some() || self.col_segs[cseg].light.is_some() {
-                continue;
-            }
-
-            let row_free = self.row_segs[rseg].free_cnt;
-            let col_free = self.col_segs[cseg].free_cnt;
-            let self_free = if self.cell_state[cell] != CellState::Blocked {
-                1
-            } else {
-                0
-            };
-            let cand_count = row_free + col_free - self_free;
-            if cand_count <= 1 {
-                continue;
-            }
-
-            let mut candidates = Vec::new();
-            for &x in &self.row_segs[rseg].cells {
-                if self.cell_state[x] != CellState::Blocked {
-                    candidates.push(x);
+        for i in 0..self.lit.words().len() {
+            let mut word = self.need_lit.words()[i] & !self.lit.words()[i];
+            while word != 0 {
+                let cell = i * 64 + word.trailing_zeros() as usize;
+                word &= word - 1;
+                let own = !self.blocked.contains(cell) as u32;
+                let cand_count = self.seg_free[layout.row_seg_id[cell]]
+                    + self.seg_free[layout.col_seg_id[cell]]
+                    - own;
+                if cand_count > 1 && best.is_none_or(|(cnt, _)| cand_count < cnt) {
+                    best = Some((cand_count, cell));
                 }
             }
-            for &x in &self.col_segs[cseg].cells {
-                if self.cell_state[x] != CellState::Blocked && !candidates.contains(&x) {
-                    candidates.push(x);
-                }
-            }
-
-            match &best {
-                None => best = Some((cand_count, candidates)),
-                Some((cnt, _)) if cand_count < *cnt => best = Some((cand_count, candidates)),
-                _ => {}
-            }
         }
 
-        best.map(|(_, cand)| cand)
+        let Some((_, cell)) = best else {
+            // 照らす必要のあるセルがすべて照らされていれば，数字の周りで分岐する
+            return (0..layout.num_cells.len())
+                .find(|&idx| self.number_counts(idx).0 < layout.num_cells[idx].value)
+                .map(|idx| {
+                    layout.num_cells[idx]
+                        .adj
+                        .iter()
+                        .copied()
+                        .filter(|&x| self.is_unknown(x))
+                        .collect()
+                });
+        };
+
+        // 行のセグメント，列のセグメントの順に並べる
+        let row = &layout.segs[layout.row_seg_id[cell]];
+        let col = &layout.segs[layout.col_seg_id[cell]];
+        let candidates = row
+            .iter()
+            .copied()
+            .filter(|&x| !self.blocked.contains(x))
+            .chain(
+                col.iter()
+                    .copied()
+                    .filter(|&x| x != cell && !self.blocked.contains(x)),
+            )
+            .collect();
+        Some(candidates)
     }
 
     fn dfs(&mut self) -> bool {
-        if !self.propagate() {
+        self.expanded(self.depth);
+        if self.stopped() || !self.settle() {
             return false;
         }
         if self.is_solved() {
+            self.observer.notify(Event::SolutionFound);
             return true;
         }
 
@@ -507,14 +1285,20 @@ impl Core {
             return false;
         };
 
+        self.stats.branches += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth + 1);
+        let cp = self.checkpoint();
         for pos in candidates {
-            let cp = self.checkpoint();
-            let mut q = VecDeque::new();
-            if self.set_light(pos, &mut q) && self.dfs() {
+            self.depth += 1;
+            let found = self.set_light(pos, Cause::Branch) && self.dfs();
+            self.depth -= 1;
+            if found {
                 return true;
             }
+            self.stats.backtracks += 1;
             self.undo(cp);
         }
+        self.release(cp);
 
         false
     }
@@ -522,8 +1306,14 @@ impl Core {
 
 #[cfg(test)]
 mod tests {
-    use super::Fast;
-    use crate::{Field, Solver};
+    use super::{Assignment, Cause, Fast, PartialResult, TraceStep, Uniqueness, UnsatCore};
+    use std::sync::{Arc, atomic::AtomicBool};
+
+    use crate::{
+        Field, Mark, PartialSolution, Solution, Solver,
+        solver::{Cancel, SolveResult},
+        validate,
+    };
 
     #[test]
     fn solve_single_cell() {
@@ -532,4 +1322,265 @@ mod tests {
         let sol = solver.solve(&field).expect("solution");
         assert!(sol.field[0][0]);
     }
+
+    #[test]
+    fn count_solutions_up_to_limit() {
+        let field = Field::from_str(2, 2, "..\n..\n").unwrap();
+        let solver = Fast::new();
+        assert_eq!(solver.count_solutions(&field, 10), 2);
+        assert_eq!(solver.count_solutions(&field, 1), 1);
+        assert_eq!(solver.count_solutions(&field, 0), 0);
+    }
+
+    #[test]
+    fn enumerate_all_solutions() {
+        let field = Field::from_str(2, 2, "..\n..\n").unwrap();
+        let mut solutions: Vec<_> = Fast::new().solutions(&field).collect();
+        solutions.sort_by_key(|sol| sol.akari_indices());
+        assert_eq!(
+            solutions,
+            vec![
+                Solution {
+                    field: vec![vec![true, false], vec![false, true]],
+                },
+                Solution {
+                    field: vec![vec![false, true], vec![true, false]],
+                },
+            ]
+        );
+        assert_eq!(solutions[0].diff(&solutions[1]).len(), 4);
+    }
+
+    #[test]
+    fn stats_count_rules_and_backtracks() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let (sol, stats) = Fast::new().solve_with_stats(&field);
+        assert!(sol.is_some());
+        assert_eq!(stats.branches, 0);
+        assert_eq!(stats.backtracks, 0);
+        assert!(stats.forced_by_number > 0);
+        assert!(stats.forced_by_segment > 0);
+        assert!(stats.max_trail > 0);
+
+        let field = Field::from_str(3, 3, "... ... ...").unwrap();
+        let (_, stats) = Fast::new().solve_with_stats(&field);
+        assert!(stats.branches > 0);
+        assert!(stats.nodes > stats.branches);
+        assert!(stats.propagation_rounds >= stats.nodes);
+    }
+
+    #[test]
+    fn lookahead_avoids_branching() {
+        let field = Field::from_str(4, 4, "0... #.1. 1... #...").unwrap();
+        let (sol, stats) = Fast::new().solve_with_stats(&field);
+        assert!(stats.branches > 0);
+
+        let (probed, stats) = Fast::new().with_lookahead(true).solve_with_stats(&field);
+        assert_eq!(stats.branches, 0);
+        assert!(stats.forced_by_probe > 0);
+        assert!(stats.probes >= stats.forced_by_probe);
+        assert_eq!(probed, sol);
+
+        // 仮置きは作業量を消費せず，ノードごとに 1 だけ消費する
+        let solver = Fast::new().with_lookahead(true);
+        let field = Field::from_str(4, 5, "..... .#... ...1. .....").unwrap();
+        let (_, stats) = solver.solve_with_stats(&field);
+        let limit = 1_000_000;
+        let cancel = Cancel::new().with_budget(limit);
+        assert!(matches!(
+            solver.solve_with(&field, &cancel),
+            SolveResult::Solved(_)
+        ));
+        assert_eq!(limit - cancel.remaining().unwrap(), stats.nodes);
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let solver = Fast::new();
+        let cancel = Cancel::new();
+        let field = Field::from_str(4, 5, "..... .#... ...1. .....").unwrap();
+        let all = solver.count_solutions(&field, usize::MAX);
+        assert!(all > 10);
+        for threads in [1, 3] {
+            assert_eq!(
+                solver.count_solutions_parallel(&field, usize::MAX, threads, &cancel),
+                Some(all)
+            );
+            assert_eq!(
+                solver.count_solutions_parallel(&field, 5, threads, &cancel),
+                Some(5)
+            );
+            let SolveResult::Solved(sol) = solver.solve_parallel(&field, threads, &cancel) else {
+                panic!("no solution");
+            };
+            assert_eq!(validate(&field, &sol), vec![]);
+        }
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        assert_eq!(
+            solver.solve_parallel(&field, 2, &cancel),
+            SolveResult::Unsolvable
+        );
+        assert_eq!(
+            solver.count_solutions_parallel(&field, 2, 2, &cancel),
+            Some(0)
+        );
+
+        // 呼び出し側の打ち切りも全スレッドに届く
+        let field = Field::from_str(6, 6, &"......\n".repeat(6)).unwrap();
+        let flag = Arc::new(AtomicBool::new(true));
+        let cancel = Cancel::new().with_flag(flag);
+        assert_eq!(
+            solver.solve_parallel(&field, 2, &cancel),
+            SolveResult::Interrupted
+        );
+        assert_eq!(
+            solver.count_solutions_parallel(&field, usize::MAX, 2, &cancel),
+            None
+        );
+    }
+
+    #[test]
+    fn classify_uniqueness() {
+        let solver = Fast::new();
+
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let answer = Solution {
+            field: vec![
+                vec![false, true, false],
+                vec![true, false, false],
+                vec![false, false, false],
+            ],
+        };
+        assert_eq!(solver.uniqueness(&field), Uniqueness::Unique(answer));
+
+        let field = Field::from_str(2, 2, "..\n..\n").unwrap();
+        assert_eq!(solver.uniqueness(&field), Uniqueness::Multiple);
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        assert_eq!(solver.uniqueness(&field), Uniqueness::NoSolution);
+
+        let field = Field::from_str(4, 4, &"....\n".repeat(4)).unwrap();
+        let cancel = Cancel::new().with_budget(1);
+        assert_eq!(
+            solver.uniqueness_with(&field, &cancel),
+            Uniqueness::Interrupted
+        );
+        assert_eq!(solver.count_solutions_with(&field, 100, &cancel), None);
+        let cancel = Cancel::new().with_budget(1000);
+        assert_eq!(
+            solver.count_solutions_with(&field, 100, &cancel),
+            Some(solver.count_solutions(&field, 100))
+        );
+    }
+
+    #[test]
+    fn trace_records_causes() {
+        let field = Field::from_str(1, 3, ".2.").unwrap();
+        let (sol, trace) = Fast::new().solve_with_trace(&field);
+        assert!(sol.is_some());
+        assert_eq!(
+            trace,
+            vec![
+                TraceStep {
+                    pos: (0, 2),
+                    assignment: Assignment::Light,
+                    cause: Cause::Number { pos: (0, 1) },
+                    depth: 0,
+                },
+                TraceStep {
+                    pos: (0, 0),
+                    assignment: Assignment::Light,
+                    cause: Cause::Number { pos: (0, 1) },
+                    depth: 0,
+                },
+            ]
+        );
+
+        let field = Field::from_str(2, 2, "..\n..\n").unwrap();
+        let (_, trace) = Fast::new().solve_with_trace(&field);
+        assert_eq!(trace[0].cause, Cause::Branch);
+        assert_eq!(trace[0].depth, 1);
+        assert_eq!(
+            trace[1].cause,
+            Cause::Segment {
+                light: trace[0].pos
+            }
+        );
+    }
+
+    #[test]
+    fn solve_from_partial_state() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let solver = Fast::new();
+
+        let mut partial = PartialSolution::new(3, 3);
+        partial.field[0][1] = Mark::Light;
+        partial.field[2][0] = Mark::Dot;
+        assert!(matches!(
+            solver.solve_partial(&field, &partial),
+            PartialResult::Solved(_)
+        ));
+
+        partial.field[1][1] = Mark::Light;
+        assert_eq!(
+            solver.solve_partial(&field, &partial),
+            PartialResult::Contradiction(vec![(1, 1)])
+        );
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        assert_eq!(
+            solver.solve_partial(&field, &PartialSolution::new(1, 3)),
+            PartialResult::Unsolvable
+        );
+        assert_eq!(
+            solver.solve_partial_with(
+                &field,
+                &PartialSolution::new(1, 3),
+                &Cancel::new().with_budget(0)
+            ),
+            PartialResult::Interrupted
+        );
+    }
+
+    #[test]
+    fn unsat_core_points_at_conflict() {
+        let solver = Fast::new();
+
+        let field = Field::from_str(3, 3, "0.0 ### 1..").unwrap();
+        assert_eq!(
+            solver.unsat_core(&field),
+            Some(UnsatCore {
+                clues: vec![(0, 2)],
+                cells: vec![(0, 1)],
+                minimal: true,
+            })
+        );
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        assert_eq!(
+            solver.unsat_core(&field),
+            Some(UnsatCore {
+                clues: vec![(0, 1)],
+                cells: vec![],
+                minimal: true,
+            })
+        );
+
+        let field = Field::from_str(1, 3, ".2.").unwrap();
+        assert_eq!(solver.unsat_core(&field), None);
+
+        // 打ち切られたら調べていない制約を残す
+        let field = Field::from_str(3, 3, "0.0 ### 1..").unwrap();
+        let cancel = Cancel::new().with_budget(1);
+        assert_eq!(
+            solver.unsat_core_with(&field, &cancel),
+            Some(UnsatCore {
+                clues: vec![(0, 0), (0, 2), (2, 0)],
+                cells: vec![(0, 1), (2, 1), (2, 2)],
+                minimal: false,
+            })
+        );
+        assert_eq!(solver.unsat_core_with(&field, &cancel), None);
+    }
 }
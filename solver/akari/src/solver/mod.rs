@@ -1,19 +1,42 @@
 //! ソルバの実装
 
+mod cdcl;
 mod cfs;
 mod cfs_with_pb;
-mod fast;
+mod dlx;
+pub(crate) mod fast;
 mod naive;
 
+pub use cdcl::Cdcl;
 pub use cfs::CFS;
 pub use cfs::CfsSolveResult;
 pub use cfs_with_pb::CFSwithPB;
+pub use dlx::Dlx;
+pub use fast::Assignment;
+pub use fast::Cause;
 pub use fast::Fast;
+pub use fast::PartialResult;
+pub use fast::SearchStats;
+pub use fast::Solutions;
+pub use fast::TraceStep;
+pub use fast::Uniqueness;
+pub use fast::UnsatCore;
 pub use naive::Naive;
 
+use std::{
+    cell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use instant::{Duration, Instant};
+
 use crate::{
-    field::{Field, Solution, State},
-    utility::{ADJ, GridUtility},
+    Event, Observer,
+    field::{Field, Solution},
+    validate::{Violation, validate},
 };
 
 const MISMATCH_AKARI: &str = "The number of lights does not match.";
@@ -47,76 +70,336 @@ impl Cell {
 /// 一時的な状態の管理
 type TempFill = Vec<Vec<Cell>>;
 
+/// 探索の打ち切り条件
+///
+/// 期限，共有フラグ，作業量の上限を組み合わせて持ち，いずれかに達すると打ち切る．
+/// 何も指定しなければ打ち切らない．
+///
+/// wasm（Cloudflare Workers など）では計算中に時計が進まないことがあるので，
+/// 期限ではなく作業量の上限で打ち切る．
+#[derive(Clone, Debug, Default)]
+pub struct Cancel {
+    deadline: Option<Instant>,
+    flags: Vec<Arc<AtomicBool>>,
+    budgets: Vec<Arc<Budget>>,
+}
+
+/// 作業量の上限と消費量（複製した [`Cancel`] の間で共有する）
+#[derive(Debug)]
+struct Budget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl Cancel {
+    /// 打ち切らない
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 今から `timeout` 後に打ち切る
+    pub fn after(timeout: Duration) -> Self {
+        Self::new().with_timeout(timeout)
+    }
+
+    /// 期限を `deadline` にする（すでに早い期限があればそちらを使う）
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+        self
+    }
+
+    /// 期限を今から `timeout` 後にする（すでに早い期限があればそちらを使う）
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// `flag` が立ったら打ち切る（複数指定すればどれか 1 つが立つと打ち切る）
+    pub fn with_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.flags.push(flag);
+        self
+    }
+
+    /// 作業量が `limit` を超えたら打ち切る（複数指定すればどれか 1 つを超えると打ち切る）
+    ///
+    /// 作業量は [`Cancel::is_cancelled`] を呼んだ回数で，ソルバは探索ノードごとに 1 回呼ぶ．
+    /// 複製した `Cancel` とは消費量を共有するので，続けて別の探索に渡すと残りの作業量で打ち切る．
+    pub fn with_budget(mut self, limit: usize) -> Self {
+        self.budgets.push(Arc::new(Budget {
+            limit,
+            used: AtomicUsize::new(0),
+        }));
+        self
+    }
+
+    /// 残りの作業量（上限がなければ `None`）
+    pub fn remaining(&self) -> Option<usize> {
+        self.budgets
+            .iter()
+            .map(|budget| {
+                budget
+                    .limit
+                    .saturating_sub(budget.used.load(Ordering::Relaxed))
+            })
+            .min()
+    }
+
+    /// 作業量を 1 消費し，打ち切るべきかを返す
+    pub fn is_cancelled(&self) -> bool {
+        for budget in &self.budgets {
+            budget.used.fetch_add(1, Ordering::Relaxed);
+        }
+        self.is_expired()
+    }
+
+    /// 作業量を消費せずに，打ち切り条件に達しているかを返す
+    pub fn is_expired(&self) -> bool {
+        self.flags.iter().any(|flag| flag.load(Ordering::Relaxed))
+            || self.deadline.is_some_and(|d| Instant::now() >= d)
+            || self
+                .budgets
+                .iter()
+                .any(|budget| budget.used.load(Ordering::Relaxed) > budget.limit)
+    }
+}
+
+/// 打ち切りを考慮した求解の結果
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveResult {
+    Solved(Solution),
+    /// 解が存在しない
+    Unsolvable,
+    /// 解が見つかる前に打ち切られた
+    Interrupted,
+}
+
+impl SolveResult {
+    /// 打ち切りを解なしとみなして解を返す
+    pub fn solution(self) -> Option<Solution> {
+        match self {
+            Self::Solved(sol) => Some(sol),
+            _ => None,
+        }
+    }
+
+    /// 探索を終えた後の結果を返す（解がなく途中で打ち切っていれば `Interrupted`）
+    ///
+    /// 探索を終えた後に打ち切り条件を調べ直すと，調べ尽くした直後に期限が過ぎただけでも
+    /// `Interrupted` になるので，探索中に打ち切ったかどうか `interrupted` を記録して渡す．
+    fn finish(found: Option<Solution>, interrupted: bool) -> Self {
+        match found {
+            Some(sol) => Self::Solved(sol),
+            None if interrupted => Self::Interrupted,
+            None => Self::Unsolvable,
+        }
+    }
+}
+
+/// 1 回の探索で参照する打ち切り条件と進捗の通知先
+pub(crate) struct Search<'a> {
+    pub(crate) cancel: &'a Cancel,
+    observer: &'a dyn Observer,
+    /// 展開したノードの数
+    nodes: cell::Cell<usize>,
+    /// 打ち切り条件に達して探索を途中でやめたか
+    interrupted: cell::Cell<bool>,
+}
+
+impl<'a> Search<'a> {
+    pub(crate) fn new(cancel: &'a Cancel, observer: &'a dyn Observer) -> Self {
+        Self {
+            cancel,
+            observer,
+            nodes: cell::Cell::new(0),
+            interrupted: cell::Cell::new(false),
+        }
+    }
+
+    /// 深さ `depth` のノードの展開を通知し，打ち切るべきかを返す
+    pub(crate) fn expand(&self, depth: usize) -> bool {
+        let nodes = self.nodes.get() + 1;
+        self.nodes.set(nodes);
+        self.observer.notify(Event::NodeExpanded { nodes, depth });
+        self.cancelled()
+    }
+
+    /// 作業量を 1 消費し，打ち切るべきかを返す（打ち切るなら記録する）
+    pub(crate) fn cancelled(&self) -> bool {
+        self.record(self.cancel.is_cancelled())
+    }
+
+    /// 作業量を消費せずに，打ち切るべきかを返す（打ち切るなら記録する）
+    pub(crate) fn expired(&self) -> bool {
+        self.record(self.cancel.is_expired())
+    }
+
+    fn record(&self, stop: bool) -> bool {
+        if stop {
+            self.interrupted.set(true);
+        }
+        stop
+    }
+
+    /// 探索を終えたことを通知する
+    pub(crate) fn finished(&self, solved: bool) {
+        if solved {
+            self.observer.notify(Event::SolutionFound);
+        }
+        self.observer.notify(Event::Finished);
+    }
+
+    /// 探索を終えたことを通知し，結果を返す
+    pub(crate) fn finish(&self, found: Option<Solution>) -> SolveResult {
+        self.finished(found.is_some());
+        SolveResult::finish(found, self.interrupted.get())
+    }
+}
+
 /// ソルバを表すトレイト
 pub trait Solver {
-    fn solve(&self, field: &Field) -> Option<Solution>;
+    fn solve(&self, field: &Field) -> Option<Solution> {
+        self.solve_with(field, &Cancel::new()).solution()
+    }
+    /// `cancel` で打ち切られるまで探索する
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult;
     /// 解 sol が条件を満たすか判定
-    fn _check(field: &Field, sol: &Solution) -> Result<(), &'static str> {
-        let (h, w) = (field.h, field.w);
-
-        // あかりの配置が条件を満たすか判定
-        for r in 0..field.h {
-            for c in 0..field.w {
-                let akari_count = (r, c).adj(h, w).filter(|&(r, c)| sol.field[r][c]).count();
-                if match field.field[r][c] {
-                    // あかりが置かれていれば x
-                    State::Nil => sol.field[r][c],
-                    State::Empty => false,
-                    // あかりが置かれているか，周囲の数と一致しなければ x
-                    State::Adj0 => sol.field[r][c] || akari_count != 0,
-                    State::Adj1 => sol.field[r][c] || akari_count != 1,
-                    State::Adj2 => sol.field[r][c] || akari_count != 2,
-                    State::Adj3 => sol.field[r][c] || akari_count != 3,
-                    State::Adj4 => sol.field[r][c] || akari_count != 4,
-                } {
-                    return Err(MISMATCH_AKARI);
-                }
+    ///
+    /// 違反をすべて知りたい場合は [`validate`] を使う．
+    fn _check(field: &Field, sol: &Solution) -> Result<(), &'static str>
+    where
+        Self: Sized,
+    {
+        match validate(field, sol).first() {
+            None => Ok(()),
+            Some(Violation::LightOnWall { .. } | Violation::NumberMismatch { .. }) => {
+                Err(MISMATCH_AKARI)
             }
+            Some(Violation::LightsSeeEachOther { .. }) => Err(OVERLAP_AKARI),
+            Some(Violation::UnlitCell { .. }) => Err(UNLIT_CELL),
         }
+    }
+}
 
-        // None: セルなし | ブロック
-        // Some(false): あかりで照らされていない
-        // Some(true): あかりで照らされている
-        let mut fill: Vec<_> = field
-            .field
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .map(|c| (c == &State::Empty).then_some(false))
-                    .collect::<Vec<_>>()
-            })
-            .collect();
-
-        // あかりが重複していないか判定
-        for r in 0..h {
-            for c in 0..w {
-                if !sol.field[r][c] {
-                    continue;
-                }
-                // その場を塗る
-                fill[r][c].replace(true);
-                for dir in ADJ {
-                    // 特定方向に塗れるだけ塗る
-                    for (nr, nc) in (r, c).while_dir(h, w, dir) {
-                        // あかりが置かれていたら失敗
-                        if sol.field[nr][nc] {
-                            return Err(OVERLAP_AKARI);
-                        }
-                        // ブロックに当たったら終了
-                        if fill[nr][nc].is_none() {
-                            break;
-                        }
-                        fill[nr][nc].replace(true);
-                    }
-                }
+#[cfg(test)]
+mod test_cancel {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use instant::Duration;
+
+    use crate::{
+        Event, Field, Observer,
+        solver::{CFS, CFSwithPB, Cancel, Cdcl, Dlx, Fast, Naive, SolveResult, Solver},
+    };
+
+    fn solvers() -> Vec<Box<dyn Solver>> {
+        vec![
+            Box::new(Fast::new()),
+            Box::new(Naive::new()),
+            Box::new(CFS::default()),
+            Box::new(CFSwithPB::default()),
+            Box::new(Cdcl::new()),
+            Box::new(Dlx::new()),
+        ]
+    }
+
+    #[test]
+    fn every_solver_honours_cancel() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let flag = Arc::new(AtomicBool::new(false));
+        let cancel = Cancel::after(Duration::from_secs(60)).with_flag(Arc::clone(&flag));
+        for solver in solvers() {
+            assert!(matches!(
+                solver.solve_with(&field, &cancel),
+                SolveResult::Solved(_)
+            ));
+        }
+
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        for solver in solvers() {
+            assert_eq!(solver.solve_with(&field, &cancel), SolveResult::Unsolvable);
+        }
+
+        flag.store(true, Ordering::Relaxed);
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        for solver in solvers() {
+            assert_eq!(solver.solve_with(&field, &cancel), SolveResult::Interrupted);
+        }
+        assert_eq!(
+            Fast::new().solve_with(&field, &Cancel::after(Duration::ZERO)),
+            SolveResult::Interrupted
+        );
+    }
+
+    /// 探索を終えたときにフラグを立てる
+    #[derive(Clone)]
+    struct RaiseOnFinish(Arc<AtomicBool>);
+
+    impl Observer for RaiseOnFinish {
+        fn notify(&self, event: Event) {
+            if event == Event::Finished {
+                self.0.store(true, Ordering::Relaxed);
             }
         }
+    }
 
-        // すべてのセルが照らされているか
-        if fill.into_iter().flatten().all(|c| c.unwrap_or(true)) {
-            Ok(())
-        } else {
-            Err(UNLIT_CELL)
+    #[test]
+    fn exhausted_search_is_not_reported_as_interrupted() {
+        // 調べ尽くした直後に打ち切り条件に達しても解なしと分かっている
+        let field = Field::from_str(1, 3, ".4.").unwrap();
+        let flag = Arc::new(AtomicBool::new(false));
+        let raise = RaiseOnFinish(Arc::clone(&flag));
+        let solvers: Vec<Box<dyn Solver>> = vec![
+            Box::new(Fast::new().with_observer(raise.clone())),
+            Box::new(Naive::new().with_observer(raise.clone())),
+            Box::new(CFS::default().with_observer(raise.clone())),
+            Box::new(CFSwithPB::default().with_observer(raise.clone())),
+            Box::new(Cdcl::new().with_observer(raise.clone())),
+            Box::new(Dlx::new().with_observer(raise)),
+        ];
+        for solver in solvers {
+            flag.store(false, Ordering::Relaxed);
+            let cancel = Cancel::new().with_flag(Arc::clone(&flag));
+            assert_eq!(solver.solve_with(&field, &cancel), SolveResult::Unsolvable);
+            assert!(cancel.is_expired());
         }
     }
+
+    #[test]
+    fn budget_interrupts_fast() {
+        let field = Field::from_str(8, 8, &"........\n".repeat(8)).unwrap();
+        let (_, stats) = Fast::new().solve_with_stats(&field);
+        assert!(stats.nodes > 2);
+
+        let cancel = Cancel::new().with_budget(2);
+        assert_eq!(
+            Fast::new().solve_with(&field, &cancel),
+            SolveResult::Interrupted
+        );
+        assert_eq!(cancel.remaining(), Some(0));
+
+        // 足りていれば解けて，残りを次の探索に使える
+        let cancel = Cancel::new().with_budget(stats.nodes + 10);
+        assert!(matches!(
+            Fast::new().solve_with(&field, &cancel),
+            SolveResult::Solved(_)
+        ));
+        assert!(cancel.remaining().is_some_and(|rest| rest >= 10));
+
+        // 上限を重ねると小さい方で打ち切り，どちらの消費量も進む
+        let outer = Cancel::new().with_budget(stats.nodes * 10);
+        let inner = outer.clone().with_budget(2);
+        assert_eq!(inner.remaining(), Some(2));
+        assert_eq!(
+            Fast::new().solve_with(&field, &inner),
+            SolveResult::Interrupted
+        );
+        assert!(!outer.is_expired());
+        assert!(
+            outer
+                .remaining()
+                .is_some_and(|rest| rest <= stats.nodes * 10 - 3)
+        );
+    }
 }
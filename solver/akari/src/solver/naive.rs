@@ -1,25 +1,57 @@
 //! 愚直な全探索
 
+use std::{fmt, sync::Arc};
+
 use crate::{
+    NullObserver, Observer,
     field::{Field, Solution},
-    solver::Solver,
+    solver::{Cancel, Search, SolveResult, Solver},
     utility::{ADJ, GridUtility},
 };
 
 /// バックトラックによる愚直な求解
-pub struct Naive;
+#[derive(Clone)]
+pub struct Naive {
+    /// 探索の進捗の通知先
+    observer: Arc<dyn Observer>,
+}
+
+impl Default for Naive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Naive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Naive").finish_non_exhaustive()
+    }
+}
 
 impl Naive {
+    pub fn new() -> Self {
+        Self {
+            observer: Arc::new(NullObserver),
+        }
+    }
+
+    /// 探索の進捗を `observer` に通知する
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
     fn rec(
         field: &Field,
         pos: usize,
         sol: Solution,
         fill: Vec<Vec<Option<bool>>>,
+        search: &Search,
         found: &mut Option<Solution>,
     ) {
         let (h, w) = (field.h, field.w);
 
-        if found.is_some() {
+        if found.is_some() || search.expand(pos) {
             return;
         }
 
@@ -59,16 +91,16 @@ impl Naive {
             }
 
             // 再帰呼び出し
-            Self::rec(field, pos + 1, new_sol, new_fill, found);
+            Self::rec(field, pos + 1, new_sol, new_fill, search, found);
         }
 
         // あかりを設置しない
-        Self::rec(field, pos + 1, sol, fill, found);
+        Self::rec(field, pos + 1, sol, fill, search, found);
     }
 }
 
 impl Solver for Naive {
-    fn solve(&self, field: &Field) -> Option<Solution> {
+    fn solve_with(&self, field: &Field, cancel: &Cancel) -> SolveResult {
         let h = field.field.len();
         let w = field.field.first().as_ref().map(|r| r.len()).unwrap_or(0);
         let sol = Solution {
@@ -85,9 +117,10 @@ impl Solver for Naive {
             .collect();
         let mut found = None;
 
-        Self::rec(field, 0, sol, fill, &mut found);
+        let search = Search::new(cancel, &*self.observer);
+        Self::rec(field, 0, sol, fill, &search, &mut found);
 
-        found
+        search.finish(found)
     }
 }
 
@@ -159,7 +192,7 @@ mod test_naive {
         let answer = Solution {
             field: vec![vec![true, false, true]],
         };
-        assert_eq!(Naive.solve(&field), Some(answer));
+        assert_eq!(Naive::new().solve(&field), Some(answer));
 
         let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
         let answer = Solution {
@@ -169,6 +202,6 @@ mod test_naive {
                 vec![false, false, false],
             ],
         };
-        assert_eq!(Naive.solve(&field), Some(answer));
+        assert_eq!(Naive::new().solve(&field), Some(answer));
     }
 }
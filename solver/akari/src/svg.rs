@@ -0,0 +1,171 @@
+//! SVG による盤面の描画
+
+use std::fmt::Write;
+
+use crate::{
+    field::{Field, Solution, State},
+    validate::{Violation, lit_cells},
+};
+
+pub(crate) const WALL_COLOR: &str = "#222222";
+pub(crate) const NUMBER_COLOR: &str = "#ffffff";
+pub(crate) const GRID_COLOR: &str = "#999999";
+pub(crate) const LIT_COLOR: &str = "#fff3b0";
+pub(crate) const BULB_COLOR: &str = "#ffb300";
+pub(crate) const HIGHLIGHT_COLOR: &str = "#e53935";
+
+/// 盤面を SVG で描く設定
+#[derive(Debug, Clone, Copy)]
+pub struct SvgRenderer {
+    /// 1 セルの一辺の長さ（px）
+    pub cell_size: usize,
+    /// 解があるとき，照らされたセルに色を塗るか
+    pub shade_lit: bool,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgRenderer {
+    pub fn new() -> Self {
+        Self {
+            cell_size: 32,
+            shade_lit: true,
+        }
+    }
+
+    /// 盤面を描く
+    ///
+    /// `solution` があれば空白セル上のあかりと照らされたセルを，
+    /// `highlights` のセルを赤で重ねて描く．
+    pub fn render(
+        &self,
+        field: &Field,
+        solution: Option<&Solution>,
+        highlights: &[(usize, usize)],
+    ) -> String {
+        let s = self.cell_size;
+        let (width, height) = (field.w * s, field.h * s);
+        let lit = solution.map(|sol| lit_cells(field, sol));
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r##"<rect width="{width}" height="{height}" fill="#ffffff"/>"##
+        )
+        .unwrap();
+
+        for r in 0..field.h {
+            for c in 0..field.w {
+                let (x, y) = (c * s, r * s);
+                let state = field.field[r][c];
+                let fill = match state {
+                    State::Empty if self.shade_lit && lit.as_ref().is_some_and(|lit| lit[r][c]) => {
+                        LIT_COLOR
+                    }
+                    State::Empty => "none",
+                    _ => WALL_COLOR,
+                };
+                writeln!(
+                    svg,
+                    r#"<rect x="{x}" y="{y}" width="{s}" height="{s}" fill="{fill}" stroke="{GRID_COLOR}"/>"#
+                )
+                .unwrap();
+
+                if let Some(n) = state.is_adj() {
+                    writeln!(
+                        svg,
+                        r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" font-weight="bold" fill="{NUMBER_COLOR}" text-anchor="middle" dominant-baseline="central">{n}</text>"#,
+                        x + s / 2,
+                        y + s / 2,
+                        s * 3 / 5,
+                    )
+                    .unwrap();
+                }
+                if state.is_empty() && solution.is_some_and(|sol| sol.field[r][c]) {
+                    writeln!(
+                        svg,
+                        r#"<circle cx="{}" cy="{}" r="{}" fill="{BULB_COLOR}" stroke="{WALL_COLOR}"/>"#,
+                        x + s / 2,
+                        y + s / 2,
+                        s * 3 / 10,
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        for &(r, c) in highlights {
+            if r < field.h && c < field.w {
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{HIGHLIGHT_COLOR}" fill-opacity="0.4" stroke="{HIGHLIGHT_COLOR}" stroke-width="2"/>"#,
+                    c * s,
+                    r * s,
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(
+            svg,
+            r#"<rect width="{width}" height="{height}" fill="none" stroke="{WALL_COLOR}" stroke-width="2"/>"#
+        )
+        .unwrap();
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// 解とその違反（[`crate::validate`] の結果）に関係するセルを赤で描く
+    pub fn render_violations(
+        &self,
+        field: &Field,
+        solution: &Solution,
+        violations: &[Violation],
+    ) -> String {
+        let cells: Vec<_> = violations.iter().flat_map(Violation::cells).collect();
+        self.render(field, Some(solution), &cells)
+    }
+}
+
+#[cfg(test)]
+mod test_svg {
+    use crate::{Field, Solver, SvgRenderer, solver::Fast, validate};
+
+    #[test]
+    fn draws_walls_bulbs_and_highlights() {
+        let field = Field::from_str(2, 3, ".1# ...").unwrap();
+        let renderer = SvgRenderer::new();
+
+        let svg = renderer.render(&field, None, &[]);
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"96\" height=\"64\"")
+        );
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches(">1</text>").count(), 1);
+        assert_eq!(svg.matches("fill=\"#222222\" stroke").count(), 2);
+        assert_eq!(svg.matches("<circle").count(), 0);
+
+        let sol = Fast::new().solve(&field).unwrap();
+        let svg = renderer.render(&field, Some(&sol), &[]);
+        assert_eq!(svg.matches("<circle").count(), sol.akari_indices().len());
+        assert_eq!(svg.matches("fill=\"#fff3b0\"").count(), 4);
+
+        // 2 つのあかりが互いを照らしている
+        let mut bad = sol.clone();
+        bad.field[1] = vec![true, true, true];
+        let violations = validate(&field, &bad);
+        let svg = renderer.render_violations(&field, &bad, &violations);
+        let cells: usize = violations.iter().map(|v| v.cells().len()).sum();
+        assert!(cells > 0);
+        assert_eq!(svg.matches("fill-opacity=\"0.4\"").count(), cells);
+    }
+}
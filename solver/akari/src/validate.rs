@@ -0,0 +1,211 @@
+//! 解の検証
+
+use crate::{
+    field::{Field, Solution, State},
+    utility::{ADJ, GridUtility},
+};
+
+/// 解が満たしていない条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// 壁（数字セルを含む）の上にあかりが置かれている
+    LightOnWall { pos: (usize, usize) },
+    /// 数字セルに隣接するあかりの数が一致しない
+    NumberMismatch {
+        pos: (usize, usize),
+        expected: usize,
+        actual: usize,
+    },
+    /// 2 つのあかりが互いを照らしている
+    LightsSeeEachOther {
+        a: (usize, usize),
+        b: (usize, usize),
+    },
+    /// 照らされていない空白セル
+    UnlitCell { pos: (usize, usize) },
+}
+
+impl Violation {
+    /// 違反に関係するセルのリスト
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        match *self {
+            Self::LightOnWall { pos }
+            | Self::NumberMismatch { pos, .. }
+            | Self::UnlitCell { pos } => vec![pos],
+            Self::LightsSeeEachOther { a, b } => vec![a, b],
+        }
+    }
+}
+
+/// 解 sol が満たしていない条件をすべて列挙する
+///
+/// 違反は「壁上のあかり・数字の不一致」「あかりの重複」「未照明セル」の順に，
+/// それぞれ行優先で並ぶ．
+pub fn validate(field: &Field, sol: &Solution) -> Vec<Violation> {
+    let (h, w) = (field.h, field.w);
+    let mut violations = Vec::new();
+
+    // 壁上のあかりと数字の不一致
+    for r in 0..h {
+        for c in 0..w {
+            let state = field.field[r][c];
+            if !state.is_empty() && sol.field[r][c] {
+                violations.push(Violation::LightOnWall { pos: (r, c) });
+            }
+            if let Some(expected) = state.is_adj() {
+                let actual = (r, c).adj(h, w).filter(|&(r, c)| sol.field[r][c]).count();
+                if actual != expected {
+                    violations.push(Violation::NumberMismatch {
+                        pos: (r, c),
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+
+    let is_light = |r: usize, c: usize| field.field[r][c].is_empty() && sol.field[r][c];
+    let lit = lit_cells(field, sol);
+
+    // あかりの重複（各組を 1 度だけ数えるため右と下のみ走査）
+    for r in 0..h {
+        for c in 0..w {
+            if !is_light(r, c) {
+                continue;
+            }
+            for dir in [ADJ[0], ADJ[3]] {
+                for (nr, nc) in (r, c).while_dir(h, w, dir) {
+                    if field.field[nr][nc] != State::Empty {
+                        break;
+                    }
+                    if sol.field[nr][nc] {
+                        violations.push(Violation::LightsSeeEachOther {
+                            a: (r, c),
+                            b: (nr, nc),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // 照らされていないセル
+    for r in 0..h {
+        for c in 0..w {
+            if field.field[r][c].is_empty() && !lit[r][c] {
+                violations.push(Violation::UnlitCell { pos: (r, c) });
+            }
+        }
+    }
+
+    violations
+}
+
+/// 空白セル上のあかりに照らされたセル
+pub(crate) fn lit_cells(field: &Field, sol: &Solution) -> Vec<Vec<bool>> {
+    let mut lit = vec![vec![false; field.w]; field.h];
+    for r in 0..field.h {
+        for c in 0..field.w {
+            if field.field[r][c].is_empty() && sol.field[r][c] {
+                for (nr, nc) in illuminated(field, (r, c)) {
+                    lit[nr][nc] = true;
+                }
+            }
+        }
+    }
+    lit
+}
+
+/// 空白セル `pos` に置いたあかりが照らすセル（`pos` 自身を含む）
+///
+/// 照らす関係は対称なので，`pos` を照らしうるあかりの位置の列挙にも使える．
+pub(crate) fn illuminated(field: &Field, pos: (usize, usize)) -> Vec<(usize, usize)> {
+    let (h, w) = (field.h, field.w);
+    let mut cells = vec![pos];
+    for dir in ADJ {
+        for (r, c) in pos.while_dir(h, w, dir) {
+            // ブロックに当たったら終了
+            if !field.field[r][c].is_empty() {
+                break;
+            }
+            cells.push((r, c));
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod test_validate {
+    use crate::{
+        field::{Field, Solution},
+        validate::{Violation, illuminated, lit_cells, validate},
+    };
+
+    #[test]
+    fn test_valid_solution() {
+        let field = Field::from_str(3, 3, "2.1 ... ..0").unwrap();
+        let sol = Solution {
+            field: vec![
+                vec![false, true, false],
+                vec![true, false, false],
+                vec![false, false, false],
+            ],
+        };
+        assert_eq!(validate(&field, &sol), vec![]);
+    }
+
+    #[test]
+    fn test_all_violations() {
+        let field = Field::from_str(3, 4, "#1.. .... ....").unwrap();
+        let sol = Solution {
+            field: vec![
+                vec![true, false, true, false],
+                vec![false, true, false, true],
+                vec![false, false, false, false],
+            ],
+        };
+        assert_eq!(
+            validate(&field, &sol),
+            vec![
+                Violation::LightOnWall { pos: (0, 0) },
+                Violation::NumberMismatch {
+                    pos: (0, 1),
+                    expected: 1,
+                    actual: 3,
+                },
+                Violation::LightsSeeEachOther {
+                    a: (1, 1),
+                    b: (1, 3),
+                },
+                Violation::UnlitCell { pos: (2, 0) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lit_cells() {
+        let field = Field::from_str(3, 3, ".#. ... ..1").unwrap();
+        assert_eq!(
+            illuminated(&field, (1, 1)),
+            vec![(1, 1), (1, 2), (1, 0), (2, 1)]
+        );
+
+        // 壁の上のあかりは照らさない
+        let sol = Solution {
+            field: vec![
+                vec![true, true, false],
+                vec![false, false, false],
+                vec![false, false, false],
+            ],
+        };
+        assert_eq!(
+            lit_cells(&field, &sol),
+            vec![
+                vec![true, false, false],
+                vec![true, false, false],
+                vec![true, false, false],
+            ]
+        );
+    }
+}
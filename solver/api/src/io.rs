@@ -1,4 +1,6 @@
-use akari::Field;
+use std::fmt;
+
+use akari::{solver::UnsatCore, Field, ParseError, Solution, Violation};
 use serde::{Deserialize, Serialize};
 
 /// Expected payload for solving a level.
@@ -12,6 +14,142 @@ pub struct SolveRequest {
 pub struct SolveResponse {
     pub solution: Option<Vec<(usize, usize)>>,
     pub error: Option<String>,
+    pub detail: Option<ErrorDetail>,
+}
+
+/// Machine-readable error code, tagged by `code`, with the values needed to locate the problem.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum ErrorDetail {
+    InvalidCell {
+        ch: char,
+    },
+    InvalidChar {
+        row: usize,
+        col: usize,
+        ch: char,
+    },
+    InvalidToken {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+    RowWidth {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    RowCount {
+        expected: usize,
+        actual: usize,
+    },
+    EmptyBoard,
+    InvalidUrl {
+        url: String,
+    },
+    InvalidHeader {
+        line: usize,
+    },
+    TooLarge {
+        h: usize,
+        w: usize,
+        max: usize,
+    },
+    LightOutOfBoard {
+        pos: (usize, usize),
+    },
+    /// The board has no solution; `clues` and `cells` form a conflicting set.
+    ///
+    /// The set is minimal only when `complete` is true. When the budget ran out while narrowing
+    /// it, the set may contain extra constraints; when it ran out before that, both lists are empty.
+    NoSolution {
+        clues: Vec<(usize, usize)>,
+        cells: Vec<(usize, usize)>,
+        complete: bool,
+    },
+    /// The solver ran out of its time budget before finishing.
+    TimedOut,
+}
+
+/// Build the detail from the result of `Fast::unsat_core_with` on an unsolvable board.
+impl From<Option<UnsatCore>> for ErrorDetail {
+    fn from(core: Option<UnsatCore>) -> Self {
+        match core {
+            Some(core) => Self::NoSolution {
+                clues: core.clues,
+                cells: core.cells,
+                complete: core.minimal,
+            },
+            None => Self::NoSolution {
+                clues: vec![],
+                cells: vec![],
+                complete: false,
+            },
+        }
+    }
+}
+
+impl From<&ParseError> for ErrorDetail {
+    fn from(err: &ParseError) -> Self {
+        match *err {
+            ParseError::InvalidCell(ch) => Self::InvalidCell { ch },
+            ParseError::InvalidChar { row, col, ch } => Self::InvalidChar { row, col, ch },
+            ParseError::InvalidToken {
+                row,
+                col,
+                ref token,
+            } => Self::InvalidToken {
+                row,
+                col,
+                token: token.clone(),
+            },
+            ParseError::RowWidth {
+                row,
+                expected,
+                actual,
+            } => Self::RowWidth {
+                row,
+                expected,
+                actual,
+            },
+            ParseError::RowCount { expected, actual } => Self::RowCount { expected, actual },
+            ParseError::Empty => Self::EmptyBoard,
+            ParseError::InvalidUrl(ref url) => Self::InvalidUrl { url: url.clone() },
+            ParseError::InvalidHeader { line } => Self::InvalidHeader { line },
+            ParseError::TooLarge { h, w, max } => Self::TooLarge { h, w, max },
+        }
+    }
+}
+
+/// Reasons a request body cannot be turned into a board.
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    Parse(ParseError),
+    LightOutOfBoard((usize, usize)),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(err) => err.fmt(f),
+            Self::LightOutOfBoard((r, c)) => write!(f, "light at ({r}, {c}) is out of the board"),
+        }
+    }
+}
+
+impl From<ParseError> for RequestError {
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<&RequestError> for ErrorDetail {
+    fn from(err: &RequestError) -> Self {
+        match err {
+            RequestError::Parse(err) => err.into(),
+            RequestError::LightOutOfBoard(pos) => Self::LightOutOfBoard { pos: *pos },
+        }
+    }
 }
 
 impl SolveResponse {
@@ -19,28 +157,125 @@ impl SolveResponse {
         Self {
             solution: Some(solution),
             error: None,
+            detail: None,
         }
     }
 
-    pub fn failed(_req: &SolveRequest, message: impl Into<String>) -> Self {
+    pub fn failed(_req: &SolveRequest, message: impl Into<String>, detail: ErrorDetail) -> Self {
         Self {
             solution: None,
             error: Some(message.into()),
+            detail: Some(detail),
         }
     }
 }
 
 impl SolveRequest {
     /// Convert the request into a parsed `Field`.
-    pub fn to_field(&self) -> Result<Field, &'static str> {
+    pub fn to_field(&self) -> Result<Field, ParseError> {
         let (h, w, normalized) = parse_level_data(&self.problem)?;
         Field::from_str(h, w, &normalized)
     }
 }
 
+/// Expected payload for checking a user's own light placement.
+#[derive(Debug, Deserialize)]
+pub struct CheckRequest {
+    pub problem: Vec<Vec<char>>,
+    pub lights: Vec<(usize, usize)>,
+}
+
+/// Response body returned by the check endpoint.
+#[derive(Debug, Serialize)]
+pub struct CheckResponse {
+    pub violations: Option<Vec<ViolationBody>>,
+    pub error: Option<String>,
+    pub detail: Option<ErrorDetail>,
+}
+
+/// A single rule violation with the cells involved.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ViolationBody {
+    LightOnWall {
+        pos: (usize, usize),
+    },
+    NumberMismatch {
+        pos: (usize, usize),
+        expected: usize,
+        actual: usize,
+    },
+    LightsSeeEachOther {
+        a: (usize, usize),
+        b: (usize, usize),
+    },
+    UnlitCell {
+        pos: (usize, usize),
+    },
+}
+
+impl From<&Violation> for ViolationBody {
+    fn from(violation: &Violation) -> Self {
+        match *violation {
+            Violation::LightOnWall { pos } => Self::LightOnWall { pos },
+            Violation::NumberMismatch {
+                pos,
+                expected,
+                actual,
+            } => Self::NumberMismatch {
+                pos,
+                expected,
+                actual,
+            },
+            Violation::LightsSeeEachOther { a, b } => Self::LightsSeeEachOther { a, b },
+            Violation::UnlitCell { pos } => Self::UnlitCell { pos },
+        }
+    }
+}
+
+impl CheckResponse {
+    pub fn checked(violations: &[Violation]) -> Self {
+        Self {
+            violations: Some(violations.iter().map(ViolationBody::from).collect()),
+            error: None,
+            detail: None,
+        }
+    }
+
+    pub fn failed(err: &RequestError) -> Self {
+        Self {
+            violations: None,
+            error: Some(err.to_string()),
+            detail: Some(err.into()),
+        }
+    }
+}
+
+impl CheckRequest {
+    /// Convert the request into a parsed `Field` and the submitted `Solution`.
+    pub fn to_field_and_solution(&self) -> Result<(Field, Solution), RequestError> {
+        let (h, w, normalized) = parse_level_data(&self.problem)?;
+        let field = Field::from_str(h, w, &normalized)?;
+
+        let mut grid = vec![vec![false; w]; h];
+        for &(r, c) in &self.lights {
+            if r >= h || c >= w {
+                return Err(RequestError::LightOutOfBoard((r, c)));
+            }
+            grid[r][c] = true;
+        }
+
+        Ok((field, Solution { field: grid }))
+    }
+}
+
 #[cfg(test)]
 mod solve_request_tests {
-    use super::SolveRequest;
+    use super::{ErrorDetail, SolveRequest};
+    use akari::{
+        solver::{Cancel, Fast},
+        Field,
+    };
 
     #[test]
     fn deserialize_problem_payload() {
@@ -57,11 +292,88 @@ mod solve_request_tests {
             ]
         );
     }
+
+    #[test]
+    fn marks_unsat_cores_cut_short_by_the_budget() {
+        let field = Field::from_str(3, 3, "0.0 ### 1..").unwrap();
+        let solver = Fast::new();
+        let detail = |cancel: &Cancel| {
+            serde_json::to_value(ErrorDetail::from(solver.unsat_core_with(&field, cancel))).unwrap()
+        };
+
+        assert_eq!(
+            detail(&Cancel::new()),
+            serde_json::json!({
+                "code": "no_solution",
+                "clues": [[0, 2]],
+                "cells": [[0, 1]],
+                "complete": true
+            })
+        );
+
+        // Interrupted while narrowing: every constraint not yet checked is kept.
+        let cancel = Cancel::new().with_budget(1);
+        assert_eq!(
+            detail(&cancel),
+            serde_json::json!({
+                "code": "no_solution",
+                "clues": [[0, 0], [0, 2], [2, 0]],
+                "cells": [[0, 1], [2, 1], [2, 2]],
+                "complete": false
+            })
+        );
+
+        // Interrupted before the board was proven unsolvable.
+        assert_eq!(
+            detail(&cancel),
+            serde_json::json!({
+                "code": "no_solution",
+                "clues": [],
+                "cells": [],
+                "complete": false
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod check_request_tests {
+    use super::{CheckRequest, CheckResponse, ViolationBody};
+
+    #[test]
+    fn reports_violations_with_positions() {
+        let json = "{\"problem\": [[\"1\",\".\"],[\".\",\".\"]], \"lights\": [[0,1],[1,1]]}";
+
+        let req: CheckRequest = serde_json::from_str(json).unwrap();
+        let (field, solution) = req.to_field_and_solution().unwrap();
+        let response = CheckResponse::checked(&akari::validate(&field, &solution));
+        assert_eq!(
+            response.violations,
+            Some(vec![ViolationBody::LightsSeeEachOther {
+                a: (0, 1),
+                b: (1, 1),
+            }])
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_board_lights() {
+        let req = CheckRequest {
+            problem: vec![vec!['.']],
+            lights: vec![(0, 1)],
+        };
+        let err = req.to_field_and_solution().unwrap_err();
+        assert_eq!(
+            serde_json::to_value(CheckResponse::failed(&err).detail).unwrap(),
+            serde_json::json!({"code": "light_out_of_board", "pos": [0, 1]})
+        );
+    }
 }
 
 #[cfg(test)]
 mod parse_level_data_tests {
-    use super::parse_level_data;
+    use super::{parse_level_data, ErrorDetail, SolveRequest};
+    use akari::ParseError;
 
     #[test]
     fn accepts_char_matrix() {
@@ -74,20 +386,50 @@ mod parse_level_data_tests {
         assert_eq!((h, w), (3, 3));
         assert_eq!(normalized, "...\n1..\n.#.\n");
     }
+
+    #[test]
+    fn reports_structured_errors() {
+        let raw = vec![vec!['.', '.'], vec!['.']];
+        assert_eq!(
+            parse_level_data(&raw),
+            Err(ParseError::RowWidth {
+                row: 1,
+                expected: 2,
+                actual: 1
+            })
+        );
+
+        let req = SolveRequest {
+            problem: vec![vec!['.', 'x']],
+        };
+        let err = req.to_field().unwrap_err();
+        assert_eq!(
+            serde_json::to_value(ErrorDetail::from(&err)).unwrap(),
+            serde_json::json!({"code": "invalid_char", "row": 0, "col": 1, "ch": "x"})
+        );
+    }
 }
 
 /// Normalize a char matrix into the format required by the solver.
-pub fn parse_level_data(level_data: &[Vec<char>]) -> Result<(usize, usize, String), &'static str> {
+pub fn parse_level_data(level_data: &[Vec<char>]) -> Result<(usize, usize, String), ParseError> {
     if level_data.is_empty() {
-        return Err("level data is empty");
+        return Err(ParseError::Empty);
     }
 
     let width = level_data[0].len();
     if width == 0 {
-        return Err("level data has zero width");
+        return Err(ParseError::Empty);
     }
-    if level_data.iter().any(|row| row.len() != width) {
-        return Err("row widths are inconsistent");
+    if let Some((row, r)) = level_data
+        .iter()
+        .enumerate()
+        .find(|(_, r)| r.len() != width)
+    {
+        return Err(ParseError::RowWidth {
+            row,
+            expected: width,
+            actual: r.len(),
+        });
     }
 
     let height = level_data.len();
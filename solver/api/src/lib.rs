@@ -1,26 +1,44 @@
 mod io;
 mod tsp;
 
-use akari::{solver, Solver};
+use akari::{
+    solver::{self, Cancel, SolveResult},
+    Solver,
+};
 use http::StatusCode;
-use io::{SolveRequest, SolveResponse};
+use io::{CheckRequest, CheckResponse, ErrorDetail, SolveRequest, SolveResponse};
 use tracing_subscriber::{
     fmt::{format::Pretty, time::UtcTime},
     prelude::*,
 };
 use tracing_web::{performance_layer, MakeConsoleWriter};
-use worker::{event, Context, Cors, Env, Method, Request, Response, Router};
+use worker::{event, Context, Cors, Env, Method, Request, Response, RouteContext, Router};
+
+/// Variable in `wrangler.toml` holding the work budget of a single request.
+///
+/// The budget counts search nodes (see `Cancel::with_budget`). A deadline would not work here,
+/// because the clock inside a Worker does not advance during pure CPU work.
+const SOLVE_BUDGET_VAR: &str = "SOLVE_BUDGET";
+
+/// Read the work budget from the environment.
+fn solve_budget<D>(ctx: &RouteContext<D>) -> Option<usize> {
+    ctx.var(SOLVE_BUDGET_VAR).ok()?.to_string().parse().ok()
+}
 
 /// Try to solve the puzzle in the request with the CFS solver.
-fn solve_request_with_cfs(req: &SolveRequest) -> (SolveResponse, StatusCode) {
+fn solve_request_with_cfs(req: &SolveRequest, budget: usize) -> (SolveResponse, StatusCode) {
     let field = match req.to_field() {
         Ok(field) => field,
-        Err(msg) => return (SolveResponse::failed(req, msg), StatusCode::BAD_REQUEST),
+        Err(err) => {
+            let response_body = SolveResponse::failed(req, err.to_string(), (&err).into());
+            return (response_body, StatusCode::BAD_REQUEST);
+        }
     };
 
     let solver = solver::Fast::new();
-    match solver.solve(&field) {
-        Some(solution) => {
+    let cancel = Cancel::new().with_budget(budget);
+    match solver.solve_with(&field, &cancel) {
+        SolveResult::Solved(solution) => {
             let mut akari = solution.akari_indices();
             if !akari.is_empty() {
                 let order = tsp::optimize_route_with_2opt(&akari, field.w);
@@ -29,13 +47,31 @@ fn solve_request_with_cfs(req: &SolveRequest) -> (SolveResponse, StatusCode) {
             let response_body = SolveResponse::solved(akari);
             (response_body, StatusCode::OK)
         }
-        None => {
-            let response_body = SolveResponse::failed(req, "No solution found");
+        SolveResult::Interrupted => {
+            let response_body =
+                SolveResponse::failed(req, "Timed out while solving", ErrorDetail::TimedOut);
+            (response_body, StatusCode::SERVICE_UNAVAILABLE)
+        }
+        SolveResult::Unsolvable => {
+            // The core search spends whatever is left of the same budget.
+            let detail = solver.unsat_core_with(&field, &cancel).into();
+            let response_body = SolveResponse::failed(req, "No solution found", detail);
             (response_body, StatusCode::OK)
         }
     }
 }
 
+/// Check a user's light placement and report every violated rule.
+fn check_request(req: &CheckRequest) -> (CheckResponse, StatusCode) {
+    match req.to_field_and_solution() {
+        Ok((field, solution)) => (
+            CheckResponse::checked(&akari::validate(&field, &solution)),
+            StatusCode::OK,
+        ),
+        Err(err) => (CheckResponse::failed(&err), StatusCode::BAD_REQUEST),
+    }
+}
+
 #[event(start)]
 fn start() {
     let fmt_layer = tracing_subscriber::fmt::layer()
@@ -61,7 +97,13 @@ async fn fetch(req: Request, env: Env, _ctx: Context) -> worker::Result<Response
 
     let resp = Router::new()
         .get("/health", |_, _| Response::ok("Daily Akari Solver!"))
-        .post_async("/", |mut req: Request, _ctx| async move {
+        .post_async("/", |mut req: Request, ctx| async move {
+            let Some(budget) = solve_budget(&ctx) else {
+                return Response::error(
+                    format!("{SOLVE_BUDGET_VAR} is not configured"),
+                    StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                );
+            };
             let payload: SolveRequest = match req.json().await {
                 Ok(body) => body,
                 Err(err) => {
@@ -72,7 +114,24 @@ async fn fetch(req: Request, env: Env, _ctx: Context) -> worker::Result<Response
                 }
             };
 
-            let (response_body, status) = solve_request_with_cfs(&payload);
+            let (response_body, status) = solve_request_with_cfs(&payload, budget);
+
+            let mut res = Response::from_json(&response_body)?;
+            res = res.with_status(status.as_u16());
+            Ok(res)
+        })
+        .post_async("/check", |mut req: Request, _ctx| async move {
+            let payload: CheckRequest = match req.json().await {
+                Ok(body) => body,
+                Err(err) => {
+                    return Response::error(
+                        format!("invalid JSON payload: {err}"),
+                        StatusCode::BAD_REQUEST.as_u16(),
+                    )
+                }
+            };
+
+            let (response_body, status) = check_request(&payload);
 
             let mut res = Response::from_json(&response_body)?;
             res = res.with_status(status.as_u16());